@@ -0,0 +1,254 @@
+use std::{collections::HashSet, fmt::Display, path::Path};
+
+const ACCESS: &str = "system.posix_acl_access";
+const DEFAULT: &str = "system.posix_acl_default";
+
+/// Version of the xattr representation (`posix_acl_xattr.h`)
+const VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    UserObj,
+    User(u32),
+    GroupObj,
+    Group(u32),
+    Mask,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    tag: Tag,
+    // rwx bits
+    perm: u32,
+}
+
+/// A POSIX ACL, decoded from its extended attributes
+#[derive(Debug, Clone, Default)]
+pub struct Acl {
+    access: Vec<Entry>,
+    // Inherited by new entries of a directory
+    default: Vec<Entry>,
+}
+
+/// Decodes a `posix_acl_xattr_header` followed by its entries
+fn decode(data: &[u8]) -> Option<Vec<Entry>> {
+    const USER_OBJ: u16 = 0x01;
+    const USER: u16 = 0x02;
+    const GROUP_OBJ: u16 = 0x04;
+    const GROUP: u16 = 0x08;
+    const MASK: u16 = 0x10;
+    const OTHER: u16 = 0x20;
+
+    let version = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+
+    if version != VERSION {
+        return None;
+    }
+
+    data[4..]
+        .chunks_exact(8)
+        .map(|c| {
+            let tag = u16::from_le_bytes([c[0], c[1]]);
+            let perm = u16::from_le_bytes([c[2], c[3]]) as u32;
+            let id = u32::from_le_bytes([c[4], c[5], c[6], c[7]]);
+
+            let tag = match tag {
+                USER_OBJ => Tag::UserObj,
+                USER => Tag::User(id),
+                GROUP_OBJ => Tag::GroupObj,
+                GROUP => Tag::Group(id),
+                MASK => Tag::Mask,
+                OTHER => Tag::Other,
+                _ => return None,
+            };
+
+            Some(Entry { tag, perm })
+        })
+        .collect()
+}
+
+fn perm_to_string(perm: u32) -> String {
+    [(0o4, 'r'), (0o2, 'w'), (0o1, 'x')]
+        .iter()
+        .map(|(bit, c)| if perm & bit != 0 { *c } else { '-' })
+        .collect()
+}
+
+impl Acl {
+    /// Reads the ACLs of `path`, `None` if it only has the permission bits
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+
+        let read = |name| {
+            xattr::get(path, name)
+                .ok()
+                .flatten()
+                .and_then(|data| decode(&data))
+                .unwrap_or_default()
+        };
+
+        let acl = Self {
+            access: read(ACCESS),
+            default: read(DEFAULT),
+        };
+
+        // Minimal ACLs (owner, group, other) are the permission bits,
+        // they are usually not even stored
+        let extended = acl
+            .access
+            .iter()
+            .any(|e| matches!(e.tag, Tag::User(_) | Tag::Group(_) | Tag::Mask));
+
+        (extended || !acl.default.is_empty()).then_some(acl)
+    }
+
+    fn mask(&self) -> Option<u32> {
+        self.access
+            .iter()
+            .find(|e| e.tag == Tag::Mask)
+            .map(|e| e.perm)
+    }
+}
+
+impl Display for Acl {
+    /// The long text form of `getfacl`, on a single line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entry_str = |e: &Entry| {
+            let (tag, qualifier) = match e.tag {
+                Tag::UserObj => ("user", String::new()),
+                Tag::User(uid) => (
+                    "user",
+                    users::get_user_by_uid(uid)
+                        .map(|u| u.name().to_string_lossy().to_string())
+                        .unwrap_or_else(|| uid.to_string()),
+                ),
+                Tag::GroupObj => ("group", String::new()),
+                Tag::Group(gid) => (
+                    "group",
+                    users::get_group_by_gid(gid)
+                        .map(|g| g.name().to_string_lossy().to_string())
+                        .unwrap_or_else(|| gid.to_string()),
+                ),
+                Tag::Mask => ("mask", String::new()),
+                Tag::Other => ("other", String::new()),
+            };
+
+            format!("{}:{}:{}", tag, qualifier, perm_to_string(e.perm))
+        };
+
+        let entries: Vec<String> = self
+            .access
+            .iter()
+            .map(entry_str)
+            .chain(
+                self.default
+                    .iter()
+                    .map(|e| format!("default:{}", entry_str(e))),
+            )
+            .collect();
+
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+/// `users::group_access_list` keeps trailing zeroes of its buffer,
+/// which would make everyone a member of root
+fn supplementary_groups() -> Vec<u32> {
+    // SAFETY: a zero size only queries the number of groups
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+
+    if count <= 0 {
+        return Vec::new();
+    }
+
+    let mut groups = vec![0; count as usize];
+
+    // SAFETY: the buffer holds `count` groups
+    let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+
+    groups.truncate(count.max(0) as usize);
+    groups
+}
+
+/// The user the access is checked for
+#[derive(Debug, Clone)]
+pub struct Identity {
+    uid: u32,
+    groups: HashSet<u32>,
+}
+
+impl Identity {
+    /// The effective user and the groups of the process,
+    /// which are the ones the kernel checks
+    pub fn current() -> Self {
+        let uid = users::get_effective_uid();
+
+        let mut groups: HashSet<u32> = supplementary_groups().into_iter().collect();
+        groups.insert(users::get_effective_gid());
+
+        Self { uid, groups }
+    }
+
+    /// What the user can do with an entry, as `rwx`,
+    /// following the ACL access check algorithm (`acl(5)`)
+    pub fn access(&self, mode: u32, uid: u32, gid: u32, acl: Option<&Acl>) -> String {
+        let owner = (mode >> 6) & 0o7;
+        let group = (mode >> 3) & 0o7;
+        let other = mode & 0o7;
+
+        // Root can read and write anything, and execute
+        // anything that is executable by someone
+        if self.uid == 0 {
+            let is_dir = mode & 0o170000 == 0o040000;
+            let exec = if is_dir || (owner | group | other) & 0o1 != 0 {
+                0o1
+            } else {
+                0
+            };
+
+            return perm_to_string(0o6 | exec);
+        }
+
+        if self.uid == uid {
+            return perm_to_string(owner);
+        }
+
+        // A directory may only have a default ACL
+        let Some(acl) = acl.filter(|a| !a.access.is_empty()) else {
+            let perm = if self.groups.contains(&gid) {
+                group
+            } else {
+                other
+            };
+
+            return perm_to_string(perm);
+        };
+
+        // With an ACL, the group bits of the mode are the mask
+        let mask = acl.mask().unwrap_or(0o7);
+
+        if let Some(entry) = acl.access.iter().find(|e| e.tag == Tag::User(self.uid)) {
+            return perm_to_string(entry.perm & mask);
+        }
+
+        let groups: Vec<u32> = acl
+            .access
+            .iter()
+            .filter(|e| match e.tag {
+                Tag::GroupObj => self.groups.contains(&gid),
+                Tag::Group(g) => self.groups.contains(&g),
+                _ => false,
+            })
+            .map(|e| e.perm)
+            .collect();
+
+        // Any matching group entry may grant each permission
+        if !groups.is_empty() {
+            let perm = groups.iter().fold(0, |acc, p| acc | p);
+            return perm_to_string(perm & mask);
+        }
+
+        perm_to_string(other)
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    fmt::Display,
+    fs::OpenOptions,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    path::Path,
+};
+
+const IMMUTABLE: u32 = 0x0000_0010;
+const APPEND: u32 = 0x0000_0020;
+
+/// Flags in the order `lsattr` prints them (`linux/fs.h`)
+const FLAGS: [(u32, char); 22] = [
+    (0x0000_0001, 's'), // secure deletion
+    (0x0000_0002, 'u'), // undelete
+    (0x0000_0008, 'S'), // synchronous updates
+    (0x0001_0000, 'D'), // synchronous directory updates
+    (IMMUTABLE, 'i'),
+    (APPEND, 'a'),
+    (0x0000_0040, 'd'), // no dump
+    (0x0000_0080, 'A'), // no atime updates
+    (0x0000_0004, 'c'), // compressed
+    (0x0000_0800, 'E'), // encrypted
+    (0x0000_4000, 'j'), // data journaling
+    (0x0000_1000, 'I'), // indexed directory
+    (0x0000_8000, 't'), // no tail merging
+    (0x0002_0000, 'T'), // top of directory hierarchy
+    (0x0008_0000, 'e'), // extents
+    (0x0080_0000, 'C'), // no copy on write
+    (0x0200_0000, 'x'), // direct access
+    (0x4000_0000, 'F'), // casefolded directory
+    (0x1000_0000, 'N'), // inline data
+    (0x2000_0000, 'P'), // project hierarchy
+    (0x0010_0000, 'V'), // verity
+    (0x0000_0400, 'm'), // don't compress
+];
+
+/// Inode flags, as set by `chattr`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InodeFlags(u32);
+
+impl InodeFlags {
+    /// Only regular files and directories are read, like `lsattr`,
+    /// opening anything else may have side effects.
+    /// `None` if the filesystem doesn't support them.
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+        let metadata = path.symlink_metadata().ok()?;
+
+        if !metadata.is_file() && !metadata.is_dir() {
+            return None;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+            .open(path)
+            .ok()?;
+
+        let mut flags: libc::c_int = 0;
+
+        // SAFETY: the kernel writes an int, despite the `long` in the definition
+        let res = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+
+        (res == 0).then_some(Self(flags as u32))
+    }
+
+    pub fn is_immutable(&self) -> bool {
+        self.0 & IMMUTABLE != 0
+    }
+
+    pub fn is_append_only(&self) -> bool {
+        self.0 & APPEND != 0
+    }
+}
+
+impl Display for InodeFlags {
+    /// Like `lsattr`, e.g. `----i---------e-------`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags: String = FLAGS
+            .iter()
+            .map(|(flag, c)| if self.0 & flag != 0 { *c } else { '-' })
+            .collect();
+
+        write!(f, "{}", flags)
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    borrow::Cow,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use strip_ansi_escapes::strip_str;
+
+/// Set once at startup by `ColorMode::init`
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    // Colors if stdout is a terminal, unless the environment says otherwise
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("invalid color mode: {}", s)),
+        }
+    }
+}
+
+impl ColorMode {
+    /// `NO_COLOR` wins over `CLICOLOR_FORCE` (https://no-color.org),
+    /// both only matter in `Auto`
+    fn resolve(self) -> bool {
+        let no_color = std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty());
+        let force = std::env::var("CLICOLOR_FORCE").is_ok_and(|v| !v.is_empty() && v != "0");
+
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto if no_color => false,
+            ColorMode::Auto if force => true,
+            // SAFETY: isatty only inspects the descriptor
+            ColorMode::Auto => unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 },
+        }
+    }
+
+    pub fn init(self) {
+        ENABLED.store(self.resolve(), Ordering::Relaxed);
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Removes the escape codes of styled text when colors are disabled,
+/// including the ones written literally in templates
+pub fn clean(s: &str) -> Cow<'_, str> {
+    if enabled() {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(strip_str(s))
+    }
+}
@@ -2,12 +2,30 @@ use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::FindArgs;
 use crate::config::{Apply, Color, Style};
+use crate::output::OutputFormat;
 use crate::walk::ThreadedWalk;
+use serde::Serialize;
 use std::io::Write;
 use std::path::Path;
+use std::process::ExitCode;
 use std::time::Instant;
 
-pub fn execute(args: &FindArgs) {
+#[derive(Serialize)]
+struct Match<'a> {
+    name: String,
+    path: String,
+    depth: usize,
+    #[serde(skip)]
+    raw: &'a Path,
+}
+
+pub fn execute(args: &FindArgs, format: OutputFormat) -> ExitCode {
+    // The walk itself skips what it can't read, the root must exist
+    if let Err(e) = std::fs::metadata(&args.root) {
+        eprintln!("pls: cannot access '{}': {}", args.root.display(), e);
+        return ExitCode::from(2);
+    }
+
     let t0 = if args.timed {
         Some(Instant::now())
     } else {
@@ -21,6 +39,7 @@ pub fn execute(args: &FindArgs) {
         .skip_hidden(!args.all)
         .max_depth(args.depth)
         .follow_symlinks(args.follow_symlinks)
+        .ignore_files(!args.no_ignore)
         .collect();
 
     let green_style = Style {
@@ -35,9 +54,9 @@ pub fn execute(args: &FindArgs) {
         text: None,
     };
 
-    let buffer: Vec<_> = paths
+    let matches: Vec<Match> = paths
         .par_iter()
-        .filter_map(|(path, _)| {
+        .filter_map(|(path, depth)| {
             path.file_name()
                 .and_then(|f| f.to_str())
                 .filter(|n| {
@@ -47,20 +66,34 @@ pub fn execute(args: &FindArgs) {
                         n.contains(&args.pattern)
                     }
                 })
-                .map(|_| {
-                    let filename = path.file_name().unwrap().to_string_lossy();
-                    let parent = path.parent().unwrap_or(Path::new(""));
+                .map(|name| Match {
+                    name: name.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    depth: *depth,
+                    raw: path,
+                })
+        })
+        .collect();
 
-                    let colored_dir = if parent.as_os_str().is_empty() {
-                        String::new()
-                    } else {
-                        green_style.apply(Some(parent.to_string_lossy().to_string() + "/"))
-                    };
+    if format != OutputFormat::Text {
+        _ = format.write(&matches, &mut lock);
+        return ExitCode::SUCCESS;
+    }
 
-                    let colored_name = red_style.apply(Some(filename.to_string()));
+    let buffer: Vec<_> = matches
+        .par_iter()
+        .map(|m| {
+            let parent = m.raw.parent().unwrap_or(Path::new(""));
 
-                    format!("{}{}", colored_dir, colored_name)
-                })
+            let colored_dir = if parent.as_os_str().is_empty() {
+                String::new()
+            } else {
+                green_style.apply(Some(parent.to_string_lossy().to_string() + "/"))
+            };
+
+            let colored_name = red_style.apply(Some(m.name.clone()));
+
+            format!("{}{}", colored_dir, colored_name)
         })
         .collect();
 
@@ -77,4 +110,6 @@ pub fn execute(args: &FindArgs) {
     } else {
         _ = writeln!(lock, "Found {} entries", count);
     }
+
+    ExitCode::SUCCESS
 }
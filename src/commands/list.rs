@@ -1,14 +1,39 @@
 use crate::{
     Args,
-    config::{FileKind, ListConfig},
-    table::Table,
+    acl::{Acl, Identity},
+    attrs::InodeFlags,
+    color,
+    config::{FileKind, Layout, ListConfig, SortKey},
+    content::{Content, Encoding, Eol},
+    dirsize::DirSizes,
+    git::{GitRepo, GitStatus},
+    link::{Link, LinkError},
+    mime::{Category, Mime},
+    output::OutputFormat,
+    sort::Sorter,
+    table::{Grid, GridDirection, Table},
+    tree::{Tree, TreeItem, TreeLine},
     util,
     walk::DirWalker,
+    xattrs::Xattrs,
+};
+use figura::{Alignment, Template, Value};
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
 };
-use chrono::{DateTime, Local};
-use figura::{Template, Value};
-use std::{cmp::Ordering, collections::HashMap, fs::DirEntry, os::unix::fs::MetadataExt};
 
+/// Raw information about an entry.
+///
+/// Serialized as is by the structured output formats,
+/// so the field names must stay stable.
+#[derive(Serialize)]
 struct FileInfo {
     name: String,
     path: String,
@@ -16,52 +41,109 @@ struct FileInfo {
     kind: FileKind,
     depth: usize,
     size: u64,
+    // Recursive size of directories, only computed if used
+    total_size: Option<u64>,
+    // Raw `st_mode`, `{mode}` in templates is the ls-style string
+    mode: u32,
+    octal: String,
     permissions: String,
-    created: String,
-    modified: String,
-    accessed: String,
+    // Seconds since the unix epoch
+    created: Option<i64>,
+    modified: Option<i64>,
+    accessed: Option<i64>,
     owner: String,
     group: String,
     nlink: u64,
+    inode: u64,
+    dev: u64,
+    // In 512-byte units, as `st_blocks`
+    blocks: u64,
+    blksize: u64,
+    // Bytes actually used on disk
+    allocated: u64,
+    uid: u32,
+    gid: u32,
+    // Device number of block and character devices
+    rdev: Option<(u32, u32)>,
+    // Fewer bytes allocated than the apparent size
+    sparse: bool,
+    git_status: Option<GitStatus>,
+    // Matched by an ignore file, only listed if ignored entries are shown
+    ignored: bool,
+    // Symlinks only
+    target: Option<String>,
+    target_resolved: Option<String>,
+    target_kind: Option<FileKind>,
+    link_chain: Vec<String>,
+    link_error: Option<LinkError>,
+    // Extended attribute names, only read if used
+    xattrs: Vec<String>,
+    selinux: Option<String>,
+    // Decoded `security.capability`, e.g. `cap_net_bind_service+ep`
+    capabilities: Option<String>,
+    // Extended ACL in the `getfacl` text form, only read if used
+    acl: Option<String>,
+    // What the current user can do, e.g. `r-x`
+    access: Option<String>,
+    // Inode flags like `lsattr`, only read if used
+    attrs: Option<String>,
+    immutable: bool,
+    append_only: bool,
+    // Content type from the first bytes, only read if used
+    mime: Option<String>,
+    category: Option<Category>,
+    // Text files only, read to the end if used
+    lines: Option<u64>,
+    words: Option<u64>,
+    encoding: Option<Encoding>,
+    eol: Option<Eol>,
+    binary: bool,
+    trailing_newline: bool,
+
+    // Timestamps formatted as in the config
+    #[serde(skip)]
+    created_fmt: String,
+    #[serde(skip)]
+    modified_fmt: String,
+    #[serde(skip)]
+    accessed_fmt: String,
+    // Branch of the repository of a file operand,
+    // the entries of a directory share the one of their section
+    #[serde(skip)]
+    git_branch: Option<String>,
+}
+
+/// Seconds since the unix epoch, negative if before it
+fn epoch_seconds(t: SystemTime) -> i64 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
 }
 
 impl FileInfo {
-    fn new(entry: DirEntry, depth: usize, config: &ListConfig) -> Self {
-        let name = entry.file_name().to_string_lossy().to_string();
-        let path = entry.path();
+    /// Fails if the entry vanished since it was listed
+    fn new(
+        path: PathBuf,
+        name: String,
+        depth: usize,
+        ignored: bool,
+        config: &ListConfig,
+    ) -> std::io::Result<Self> {
         let extension = path
             .extension()
             .map(|e| e.to_string_lossy().to_string())
             .unwrap_or_default();
 
-        let (kind, meta) = FileKind::from_path(&path);
+        let (kind, meta) = FileKind::from_path(&path)?;
 
         let size = meta.len();
-        let permissions = util::permissions_to_string(meta.mode());
-
-        let created = meta
-            .created()
-            .map(|t| {
-                let dt: DateTime<Local> = t.into();
-                dt.format(&config.created_fmt).to_string()
-            })
-            .unwrap_or_else(|_| "N/A".to_string());
-
-        let modified = meta
-            .modified()
-            .map(|t| {
-                let dt: DateTime<Local> = t.into();
-                dt.format(&config.modified_fmt).to_string()
-            })
-            .unwrap_or_else(|_| "N/A".to_string());
+        let mode = meta.mode();
+        let permissions = util::permissions_to_string(mode);
 
-        let accessed = meta
-            .accessed()
-            .map(|t| {
-                let dt: DateTime<Local> = t.into();
-                dt.format(&config.accessed_fmt).to_string()
-            })
-            .unwrap_or_else(|_| "N/A".to_string());
+        let created = meta.created().ok();
+        let modified = meta.modified().ok();
+        let accessed = meta.accessed().ok();
 
         let owner = if cfg!(unix) {
             users::get_user_by_uid(meta.uid())
@@ -80,22 +162,76 @@ impl FileInfo {
         };
 
         let nlink = meta.nlink();
+        let allocated = meta.blocks() * 512;
+        let rdev = matches!(kind, FileKind::BlockDevice | FileKind::CharDevice)
+            .then(|| util::major_minor(meta.rdev()));
+        let link = Link::read(&path);
+        let resolved = link.as_ref().and_then(|l| l.resolved.clone());
 
-        Self {
+        Ok(Self {
             name,
             path: path.to_string_lossy().to_string(),
             extension,
             kind,
             depth: depth - 1,
             size,
+            total_size: None,
+            mode,
+            octal: util::mode_to_octal(mode),
             permissions,
-            created,
-            modified,
-            accessed,
+            created: created.map(epoch_seconds),
+            modified: modified.map(epoch_seconds),
+            accessed: accessed.map(epoch_seconds),
             owner,
             group,
             nlink,
-        }
+            inode: meta.ino(),
+            dev: meta.dev(),
+            blocks: meta.blocks(),
+            blksize: meta.blksize(),
+            allocated,
+            uid: meta.uid(),
+            gid: meta.gid(),
+            rdev,
+            sparse: meta.is_file() && allocated < size,
+            git_status: None,
+            ignored,
+            target: link.as_ref().map(|l| l.target.clone()),
+            target_resolved: resolved.as_ref().map(|r| r.to_string_lossy().to_string()),
+            target_kind: resolved
+                .and_then(|r| FileKind::from_path(r).ok())
+                .map(|(kind, _)| kind),
+            link_chain: link
+                .as_ref()
+                .map(|l| {
+                    l.chain
+                        .iter()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            link_error: link.and_then(|l| l.error),
+            xattrs: Vec::new(),
+            selinux: None,
+            capabilities: None,
+            acl: None,
+            access: None,
+            attrs: None,
+            immutable: false,
+            append_only: false,
+            mime: None,
+            category: None,
+            lines: None,
+            words: None,
+            encoding: None,
+            eol: None,
+            binary: false,
+            trailing_newline: false,
+            created_fmt: config.created_fmt.format(created),
+            modified_fmt: config.modified_fmt.format(modified),
+            accessed_fmt: config.accessed_fmt.format(accessed),
+            git_branch: None,
+        })
     }
 }
 
@@ -106,44 +242,275 @@ fn insert_info_raw(map: &mut HashMap<&'static str, Value>, f: &FileInfo) {
     map.insert("kind", Value::String(f.kind.to_string()));
     map.insert("depth", Value::Int(f.depth as i64));
     map.insert("size", Value::Int(f.size as i64));
+    map.insert(
+        "total_size",
+        Value::Int(f.total_size.unwrap_or(f.size) as i64),
+    );
     map.insert("permissions", Value::String(f.permissions.to_string()));
-    map.insert("created", Value::String(f.created.to_string()));
-    map.insert("modified", Value::String(f.modified.to_string()));
-    map.insert("accessed", Value::String(f.accessed.to_string()));
+    // Like ls, `+` marks entries with an extended ACL
+    let acl_marker = if f.acl.is_some() { "+" } else { "" };
+
+    map.insert(
+        "mode",
+        Value::String(format!("{}{}", util::mode_to_string(f.mode), acl_marker)),
+    );
+    map.insert("acl", Value::String(f.acl.clone().unwrap_or_default()));
+    map.insert("attrs", Value::String(f.attrs.clone().unwrap_or_default()));
+    map.insert("immutable", Value::Bool(f.immutable));
+    map.insert("append_only", Value::Bool(f.append_only));
+    map.insert("mime", Value::String(f.mime.clone().unwrap_or_default()));
+    map.insert(
+        "category",
+        Value::String(f.category.map(|c| c.to_string()).unwrap_or_default()),
+    );
+    map.insert(
+        "access",
+        Value::String(f.access.clone().unwrap_or_default()),
+    );
+    for (field, count) in [("lines", f.lines), ("words", f.words)] {
+        map.insert(
+            field,
+            count.map_or(Value::String(String::new()), |n| Value::Int(n as i64)),
+        );
+    }
+    map.insert(
+        "encoding",
+        Value::String(f.encoding.map(|e| e.to_string()).unwrap_or_default()),
+    );
+    map.insert(
+        "eol",
+        Value::String(f.eol.map(|e| e.to_string()).unwrap_or_default()),
+    );
+    map.insert("binary", Value::Bool(f.binary));
+    map.insert("trailing_newline", Value::Bool(f.trailing_newline));
+    map.insert("octal", Value::String(f.octal.to_string()));
+    map.insert("user_perms", Value::String(f.permissions[0..3].to_string()));
+    map.insert(
+        "group_perms",
+        Value::String(f.permissions[3..6].to_string()),
+    );
+    map.insert(
+        "other_perms",
+        Value::String(f.permissions[6..9].to_string()),
+    );
+    map.insert("created", Value::String(f.created_fmt.to_string()));
+    map.insert("modified", Value::String(f.modified_fmt.to_string()));
+    map.insert("accessed", Value::String(f.accessed_fmt.to_string()));
+    map.insert("created_epoch", Value::Int(f.created.unwrap_or_default()));
+    map.insert("modified_epoch", Value::Int(f.modified.unwrap_or_default()));
+    map.insert("accessed_epoch", Value::Int(f.accessed.unwrap_or_default()));
     map.insert("owner", Value::String(f.owner.to_string()));
     map.insert("group", Value::String(f.group.to_string()));
     map.insert("nlink", Value::Int(f.nlink as i64));
+    map.insert("inode", Value::Int(f.inode as i64));
+    map.insert("dev", Value::Int(f.dev as i64));
+    map.insert("blocks", Value::Int(f.blocks as i64));
+    map.insert("blksize", Value::Int(f.blksize as i64));
+    map.insert("allocated", Value::Int(f.allocated as i64));
+    map.insert("uid", Value::Int(f.uid as i64));
+    map.insert("gid", Value::Int(f.gid as i64));
+    map.insert(
+        "rdev",
+        Value::String(
+            f.rdev
+                .map(|(major, minor)| format!("{}, {}", major, minor))
+                .unwrap_or_default(),
+        ),
+    );
+    map.insert("sparse", Value::Bool(f.sparse));
+    map.insert(
+        "git_status",
+        Value::String(f.git_status.map(|s| s.to_string()).unwrap_or_default()),
+    );
+    map.insert("ignored", Value::Bool(f.ignored));
+    map.insert(
+        "target",
+        Value::String(f.target.clone().unwrap_or_default()),
+    );
+    map.insert(
+        "target_resolved",
+        Value::String(f.target_resolved.clone().unwrap_or_default()),
+    );
+    map.insert(
+        "target_kind",
+        Value::String(f.target_kind.map(|k| k.to_string()).unwrap_or_default()),
+    );
+    map.insert("link_chain", Value::String(f.link_chain.join(" -> ")));
+    map.insert(
+        "link_error",
+        Value::String(f.link_error.map(|e| e.to_string()).unwrap_or_default()),
+    );
+    map.insert(
+        "xattrs",
+        Value::String(if f.xattrs.is_empty() { "" } else { "@" }.to_string()),
+    );
+    map.insert("xattr_list", Value::String(f.xattrs.join(", ")));
+    map.insert(
+        "selinux",
+        Value::String(f.selinux.clone().unwrap_or_default()),
+    );
+    map.insert(
+        "capabilities",
+        Value::String(f.capabilities.clone().unwrap_or_default()),
+    );
+    // Styled in `apply_styles`, as the target needs the context
+    map.insert("link", Value::String(String::new()));
+}
+
+/// Styles that color mode strings one character at a time
+const PERMISSION_STYLES: [&str; 6] = [
+    "permissions_type",
+    "permissions_read",
+    "permissions_write",
+    "permissions_exec",
+    "permissions_special",
+    "permissions_none",
+];
+
+/// Variables holding mode strings
+const PERMISSION_FIELDS: [&str; 5] = [
+    "permissions",
+    "mode",
+    "user_perms",
+    "group_perms",
+    "other_perms",
+];
+
+/// Styles each character of a mode string by what it grants,
+/// `has_type` if the first character is the file type (as in `{mode}`)
+fn style_permissions(
+    perms: &str,
+    has_type: bool,
+    config: &ListConfig,
+    context: &HashMap<&'static str, Value>,
+) -> String {
+    perms
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let key = match c {
+                _ if has_type && i == 0 => "permissions_type",
+                'r' => "permissions_read",
+                'w' => "permissions_write",
+                'x' => "permissions_exec",
+                's' | 'S' | 't' | 'T' | '+' => "permissions_special",
+                _ => "permissions_none",
+            };
+
+            match config.styles.get(key) {
+                Some(style) => style.resolve(Some(c.to_string()), context),
+                None => c.to_string(),
+            }
+        })
+        .collect()
 }
 
 fn apply_styles(map: &mut HashMap<&'static str, Value>, config: &ListConfig, args: &Args) {
     let context = map.clone();
 
-    if args.pad_names && args.all {
-        if let Some(Value::String(name)) = map.get_mut("name") {
-            if !name.starts_with('.') {
-                *name = format!(" {}", name);
+    // The target is styled like a name, by its own kind
+    // (or as a broken symlink), unless it has its own style
+    if let Some(Value::String(target)) = context.get("target")
+        && !target.is_empty()
+    {
+        let mut target_context = context.clone();
+
+        let kind = match context.get("target_kind") {
+            Some(Value::String(k)) if !k.is_empty() => k.to_string(),
+            _ => FileKind::BrokenSymlink.to_string(),
+        };
+
+        let extension = Path::new(target)
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        target_context.insert("kind", Value::String(kind));
+        target_context.insert("name", Value::String(target.to_string()));
+        target_context.insert("extension", Value::String(extension));
+
+        let target = match config
+            .styles
+            .get("target")
+            .or_else(|| config.styles.get("name"))
+        {
+            Some(style) => style.resolve(Some(target.to_string()), &target_context),
+            None => target.to_string(),
+        };
+
+        map.insert(
+            "link",
+            Value::String(format!("{}{}", config.link_arrow, target)),
+        );
+        map.insert("target", Value::String(target));
+    }
+
+    // Per character styles replace the styles of the whole strings
+    let per_character = PERMISSION_STYLES
+        .iter()
+        .any(|k| config.styles.contains_key(*k));
+
+    if per_character {
+        for field in PERMISSION_FIELDS {
+            if let Some(Value::String(s)) = map.get_mut(field) {
+                *s = style_permissions(s, field == "mode", config, &context);
             }
         }
     }
 
+    if args.pad_names
+        && args.all
+        && let Some(Value::String(name)) = map.get_mut("name")
+        && !name.starts_with('.')
+    {
+        *name = format!(" {}", name);
+    }
+
     for field in [
         "name",
         "path",
         "kind",
         "icon",
+        "indent",
         "size",
         "permissions",
+        "mode",
+        "octal",
+        "user_perms",
+        "group_perms",
+        "other_perms",
         "created",
         "modified",
         "accessed",
         "owner",
         "group",
         "nlink",
+        "rdev",
+        "git_status",
+        "git_branch",
+        "target_resolved",
+        "link_chain",
+        "link_error",
+        "xattrs",
+        "xattr_list",
+        "selinux",
+        "capabilities",
+        "acl",
+        "access",
+        "attrs",
+        "mime",
+        "category",
+        "encoding",
+        "eol",
     ] {
-        if let Some(Value::String(s)) = map.get_mut(field) {
-            if let Some(style) = config.styles.get(field) {
-                *s = style.resolve(Some(s.to_string()), &context);
-            }
+        if per_character && PERMISSION_FIELDS.contains(&field) {
+            continue;
+        }
+
+        if let Some(Value::String(s)) = map.get_mut(field)
+            && let Some(style) = config.styles.get(field)
+        {
+            *s = style.resolve(Some(s.to_string()), &context);
         }
     }
 
@@ -157,60 +524,251 @@ fn apply_styles(map: &mut HashMap<&'static str, Value>, config: &ListConfig, arg
         map.insert("depth_str", Value::String(d));
     }
 
-    if let Some(Value::Int(size)) = map.get("size") {
-        let mut s = config.size_unit.format_bytes(*size as u64);
+    for field in ["size", "total_size", "allocated"] {
+        if let Some(Value::Int(size)) = map.get(field) {
+            let mut s = config.size_unit.format_bytes(*size as u64);
+
+            if let Some(style) = config.styles.get(field) {
+                s = style.resolve(Some(s), &context);
+            }
+
+            map.insert(field, Value::String(s));
+        }
+    }
+}
+
+/// Expands the operands that are glob patterns the shell didn't expand
+/// (e.g. quoted, or from a shell without globbing).
+///
+/// Each operand yields its paths, or an error message.
+fn expand_operands(operands: &[PathBuf], all: bool) -> Vec<Result<PathBuf, String>> {
+    let options = glob::MatchOptions {
+        require_literal_leading_dot: !all,
+        ..Default::default()
+    };
+
+    let mut expanded = Vec::new();
+
+    for operand in operands {
+        let error = match std::fs::symlink_metadata(operand) {
+            Ok(_) => {
+                expanded.push(Ok(operand.clone()));
+                continue;
+            }
+
+            Err(e) => e,
+        };
+
+        let pattern = operand.to_string_lossy();
+
+        // Existing paths are never patterns, even if they contain `*`
+        if !pattern.contains(['*', '?', '[']) {
+            expanded.push(Err(format!(
+                "cannot access '{}': {}",
+                operand.display(),
+                error
+            )));
+            continue;
+        }
+
+        match glob::glob_with(&pattern, options) {
+            Ok(paths) => {
+                let paths: Vec<PathBuf> = paths.filter_map(|p| p.ok()).collect();
+
+                if paths.is_empty() {
+                    expanded.push(Err(format!("no matches found: '{}'", pattern)));
+                }
+
+                expanded.extend(paths.into_iter().map(Ok));
+            }
 
-        if let Some(style) = config.styles.get("size") {
-            s = style.resolve(Some(s), &context);
+            Err(e) => expanded.push(Err(format!("invalid pattern '{}': {}", pattern, e))),
         }
+    }
+
+    expanded
+}
 
-        map.insert("size", Value::String(s));
+/// Prints the error of an entry that can't be read, like a bad operand,
+/// so that the rest is still listed
+fn reported(info: std::io::Result<FileInfo>, path: &Path, failed: &mut bool) -> Option<FileInfo> {
+    match info {
+        Ok(info) => Some(info),
+        Err(e) => {
+            eprintln!("pls: cannot access '{}': {}", path.display(), e);
+            *failed = true;
+            None
+        }
     }
 }
 
-pub fn execute(args: &Args, config: &ListConfig) {
+/// Fills the git branch (and status, if used) of file operands,
+/// each one is looked up in the repository of its parent directory
+fn load_file_repos(infos: &mut [FileInfo], statuses: bool) {
+    let mut repos: HashMap<PathBuf, Option<GitRepo>> = HashMap::new();
+
+    for info in infos {
+        let path = Path::new(&info.path);
+
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let repo = repos.entry(parent.clone()).or_insert_with(|| {
+            let mut repo = GitRepo::open(&parent);
+
+            if statuses && let Some(repo) = &mut repo {
+                repo.load_statuses();
+            }
+
+            repo
+        });
+
+        if let Some(repo) = repo {
+            info.git_branch = repo.branch();
+
+            if statuses {
+                info.git_status = repo.status(parent.join(name));
+            }
+        }
+    }
+}
+
+fn tree_items<'a>(infos: &'a [FileInfo], args: &Args) -> Vec<TreeItem<'a>> {
+    infos
+        .iter()
+        .map(|info| TreeItem {
+            depth: info.depth,
+            is_dir: info.kind.is_dir(),
+            // The walker counts the entries of the listed directory as depth 1
+            expanded: info.kind.is_dir() && info.depth + 1 < args.depth,
+            name: &info.name,
+        })
+        .collect()
+}
+
+/// Flags the entries drawn by the tree, the ones behind a "… N more" line are not
+fn tree_shown(infos: &[FileInfo], args: &Args, config: &ListConfig) -> Vec<bool> {
+    let items = tree_items(infos, args);
+    let mut shown = vec![false; infos.len()];
+
+    for line in Tree::new(&items, &config.tree).lines() {
+        if let TreeLine::Entry { index, .. } = line {
+            shown[index] = true;
+        }
+    }
+
+    shown
+}
+
+/// Prints the styled entries of a single section
+fn print_entries(
+    infos: &[FileInfo],
+    git_branch: &str,
+    args: &Args,
+    config: &ListConfig,
+    layout: Layout,
+    show_total: bool,
+) {
     let templates = config
         .format
         .iter()
         .filter_map(|s| Template::<'{', '}'>::parse(s).ok())
         .collect::<Vec<_>>();
 
+    let grid_template = Template::<'{', '}'>::parse(&config.grid_format).ok();
+
     let mut context = HashMap::new();
     let mut table = Table::new().padding(config.padding);
+    let mut grid = Grid::new(
+        match layout {
+            Layout::Across => GridDirection::Across,
+            _ => GridDirection::Down,
+        },
+        util::terminal_width(),
+    )
+    .padding(config.padding);
     let mut row = Vec::new();
 
     if !config.headers.is_empty() {
         table.add_headers(config.headers.as_slice());
     }
 
-    for (entry, depth) in DirWalker::new(&args.path)
-        .max_depth(args.depth)
-        .skip_hidden(!args.all)
-        .follow_symlinks(args.follow_symlinks)
-        .sort_by(|a, b| {
-            // Sort directories first, then files, then symlinks
-            let a_is_dir = a.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
-            let b_is_dir = b.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+    let lines = if args.tree {
+        let items = tree_items(infos, args);
+        Tree::new(&items, &config.tree).lines()
+    } else {
+        infos
+            .iter()
+            .enumerate()
+            .map(|(index, info)| TreeLine::Entry {
+                index,
+                prefix: " ".repeat(info.depth),
+                name: None,
+            })
+            .collect()
+    };
+
+    // Old formats indent with `{ :depth}`, so the tree
+    // must be drawn even if `{indent}` is not used
+    let prepend_indent = args.tree && !util::template_variables(&config.format).contains("indent");
+
+    for line in lines {
+        let (index, prefix, name) = match line {
+            TreeLine::Entry {
+                index,
+                prefix,
+                name,
+            } => (index, prefix, name),
 
-            match (a_is_dir, b_is_dir) {
-                (true, false) => Ordering::Less,
-                (false, true) => Ordering::Greater,
-                _ => a.file_name().cmp(&b.file_name()),
+            TreeLine::More { prefix, count } => {
+                let prefix = match config.styles.get("indent") {
+                    Some(style) => style.resolve(Some(prefix), &context),
+                    None => prefix,
+                };
+
+                table.add_row([(format!("{}… {} more", prefix, count), Alignment::Left)]);
+                continue;
             }
-        })
-    {
-        let info = FileInfo::new(entry, depth, config);
+        };
+
+        insert_info_raw(&mut context, &infos[index]);
+
+        if let Some(name) = name {
+            context.insert("name", Value::String(name));
+        }
 
-        insert_info_raw(&mut context, &info);
+        context.insert("indent", Value::String(prefix));
+        let git_branch = infos[index].git_branch.as_deref().unwrap_or(git_branch);
+        context.insert("git_branch", Value::String(git_branch.to_string()));
 
         // Resolve icon after raw data
         // Because it must be resolved like styles,
         // So we need to push raw data in first
-        let icon = config.icon.resolve(None, &context);
+        let icon = config.icons.resolve(
+            &config.icon,
+            &infos[index].name,
+            infos[index].kind,
+            &context,
+        );
 
         context.insert("icon", Value::String(icon));
 
-        apply_styles(&mut context, &config, args);
+        apply_styles(&mut context, config, args);
+
+        if layout != Layout::Table {
+            if let Some(Ok(cell)) = grid_template.as_ref().map(|t| t.format(&context)) {
+                grid.add_cell(cell);
+            }
+
+            context.clear();
+            continue;
+        }
 
         for t in &templates {
             if let Ok(output) = t.format(&context) {
@@ -218,12 +776,296 @@ pub fn execute(args: &Args, config: &ListConfig) {
             }
         }
 
+        if prepend_indent
+            && let (Some((cell, _)), Some(Value::String(indent))) =
+                (row.first_mut(), context.get("indent"))
+        {
+            cell.insert_str(0, indent);
+        }
+
         table.add_row(row.as_slice());
 
         row.clear();
         context.clear();
     }
 
-    println!("total {}", table.rows().len());
-    println!("{}", table);
+    if layout != Layout::Table {
+        println!("{}", color::clean(&grid.to_string()));
+        return;
+    }
+
+    if show_total {
+        println!("total {}", infos.len());
+    }
+
+    println!("{}", color::clean(&table.to_string()));
+}
+
+/// Lists every operand: file operands first, as a single section,
+/// then each directory in its own section (with a header if there are several).
+///
+/// Operands that can't be listed are reported without stopping the others,
+/// and make the exit code 2, like ls.
+pub fn execute(args: &Args, config: &ListConfig, format: OutputFormat) -> ExitCode {
+    let layout = if args.tree {
+        Layout::Table
+    } else if args.across {
+        Layout::Across
+    } else if args.grid {
+        Layout::Grid
+    } else {
+        args.layout.unwrap_or(config.layout)
+    };
+
+    let sort_keys = args.sort.clone().unwrap_or_else(|| config.sort.clone());
+    let ignore_files = config.ignore_files && !args.no_ignore;
+
+    // Shared with the sorter, so each directory is only walked once
+    let sizes = Arc::new(DirSizes::new(!args.all, ignore_files));
+    // Structured output only gets the expensive fields on demand
+    let all_fields = args.all_fields && format != OutputFormat::Text;
+    let total_size =
+        config.uses("total_size") || sort_keys.contains(&SortKey::TotalSize) || all_fields;
+    let git_status = config.uses("git_status") || all_fields;
+
+    let sorter = Sorter::new(
+        sort_keys,
+        args.reverse || config.reverse,
+        config.group_order.clone(),
+        Arc::clone(&sizes),
+    );
+
+    let xattrs = ["xattrs", "xattr_list", "selinux", "capabilities"]
+        .iter()
+        .any(|v| config.uses(v))
+        || all_fields;
+
+    // The mode string needs them for its `+` marker
+    let acls = ["acl", "access", "mode"].iter().any(|v| config.uses(v)) || all_fields;
+    let identity = Identity::current();
+    let attrs = ["attrs", "immutable", "append_only"]
+        .iter()
+        .any(|v| config.uses(v))
+        || all_fields;
+    let mime = config.uses("mime") || config.uses("category") || all_fields;
+    let content = [
+        "lines",
+        "words",
+        "encoding",
+        "eol",
+        "binary",
+        "trailing_newline",
+    ]
+    .iter()
+    .any(|v| config.uses(v))
+        || all_fields;
+
+    // Fills the fields that are too expensive to compute if unused
+    let fill_expensive = |infos: &mut [&mut FileInfo]| {
+        if total_size {
+            for info in infos.iter_mut() {
+                info.total_size = Some(if info.kind.is_dir() {
+                    sizes.get(&info.path)
+                } else {
+                    info.size
+                });
+            }
+        }
+
+        if xattrs {
+            infos.par_iter_mut().for_each(|info| {
+                let xattrs = Xattrs::read(&info.path);
+
+                info.xattrs = xattrs.names;
+                info.selinux = xattrs.selinux;
+                info.capabilities = xattrs.capabilities;
+            });
+        }
+
+        if acls {
+            infos.par_iter_mut().for_each(|info| {
+                let acl = Acl::read(&info.path);
+
+                info.access = Some(identity.access(info.mode, info.uid, info.gid, acl.as_ref()));
+                info.acl = acl.map(|a| a.to_string());
+            });
+        }
+
+        if attrs {
+            infos.par_iter_mut().for_each(|info| {
+                if let Some(flags) = InodeFlags::read(&info.path) {
+                    info.attrs = Some(flags.to_string());
+                    info.immutable = flags.is_immutable();
+                    info.append_only = flags.is_append_only();
+                }
+            });
+        }
+
+        if mime {
+            infos.par_iter_mut().for_each(|info| {
+                let detected = Mime::detect(&info.path);
+
+                info.mime = Some(detected.mime);
+                info.category = detected.category;
+            });
+        }
+
+        if content {
+            infos
+                .par_iter_mut()
+                .for_each(|info| match Content::read(&info.path) {
+                    Some(Content::Text(stats)) => {
+                        // Partial counts would look exact, so they are left unknown
+                        info.lines = (!stats.truncated).then_some(stats.lines);
+                        info.words = (!stats.truncated).then_some(stats.words);
+                        info.encoding = Some(stats.encoding);
+                        info.eol = stats.eol;
+                        info.trailing_newline = stats.trailing_newline;
+                    }
+                    Some(Content::Binary) => info.binary = true,
+                    None => {}
+                });
+        }
+    };
+
+    let operands = expand_operands(&args.paths, args.all);
+
+    // Like ls, sections get a header as soon as there is more than one operand
+    let section_headers = operands.len() > 1;
+
+    let mut failed = false;
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+
+    for operand in operands {
+        match operand {
+            Ok(path) if path.is_dir() => dirs.push(path),
+            Ok(path) => files.push(path),
+            Err(e) => {
+                eprintln!("pls: {}", e);
+                failed = true;
+            }
+        }
+    }
+
+    sorter.sort_paths(&mut files);
+    sorter.sort_paths(&mut dirs);
+
+    // Everything listed, for the structured formats
+    let mut records = Vec::new();
+    let mut printed = false;
+
+    if !files.is_empty() {
+        let mut infos: Vec<FileInfo> = files
+            .into_iter()
+            .filter_map(|path| {
+                let name = path.to_string_lossy().to_string();
+                let info = FileInfo::new(path.clone(), name, 1, false, config);
+                reported(info, &path, &mut failed)
+            })
+            .collect();
+
+        fill_expensive(&mut infos.iter_mut().collect::<Vec<_>>());
+
+        if git_status || format == OutputFormat::Text {
+            load_file_repos(&mut infos, git_status);
+        }
+
+        if format == OutputFormat::Text {
+            print_entries(&infos, "", args, config, layout, false);
+            printed = true;
+        } else {
+            records.extend(infos);
+        }
+    }
+
+    for dir in dirs {
+        let walker = match DirWalker::new(&dir) {
+            Ok(walker) => walker,
+            Err(e) => {
+                eprintln!("pls: cannot open directory '{}': {}", dir.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let sorter = sorter.clone();
+
+        let mut infos: Vec<FileInfo> = walker
+            .max_depth(args.depth)
+            .skip_hidden(!args.all)
+            .follow_symlinks(args.follow_symlinks)
+            .sort_by(move |entries| sorter.sort(entries))
+            .ignore_files(ignore_files)
+            .show_ignored(config.show_ignored || args.show_ignored)
+            .filter_map(|(entry, depth, ignored)| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let info = FileInfo::new(entry.path(), name, depth, ignored, config);
+                reported(info, &entry.path(), &mut failed)
+            })
+            .collect();
+
+        // Entries cut from the tree are not worth reading
+        if format == OutputFormat::Text && args.tree && config.tree.max_entries > 0 {
+            let shown = tree_shown(&infos, args, config);
+
+            fill_expensive(
+                &mut infos
+                    .iter_mut()
+                    .zip(shown)
+                    .filter_map(|(info, shown)| shown.then_some(info))
+                    .collect::<Vec<_>>(),
+            );
+        } else {
+            fill_expensive(&mut infos.iter_mut().collect::<Vec<_>>());
+        }
+
+        let mut repo = GitRepo::open(&dir);
+        let git_branch = repo.as_ref().and_then(|r| r.branch()).unwrap_or_default();
+
+        if git_status && let Some(repo) = &mut repo {
+            repo.load_statuses();
+
+            for info in &mut infos {
+                info.git_status = repo.status(&info.path);
+            }
+        }
+
+        if format != OutputFormat::Text {
+            records.extend(infos);
+            continue;
+        }
+
+        if printed {
+            println!();
+        }
+
+        if section_headers {
+            println!("{}:", dir.display());
+        }
+
+        if config.git_header
+            && let Some(header) = repo.as_ref().and_then(|r| r.header())
+        {
+            let header = match config.styles.get("git_branch") {
+                Some(style) => style.resolve(Some(header), &HashMap::new()),
+                None => header,
+            };
+
+            println!("git {}", header);
+        }
+
+        print_entries(&infos, &git_branch, args, config, layout, true);
+        printed = true;
+    }
+
+    if format != OutputFormat::Text {
+        _ = format.write(&records, &mut std::io::stdout().lock());
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    }
 }
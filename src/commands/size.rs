@@ -1,55 +1,82 @@
-use crate::{SizeArgs, config::SizeUnit, walk::ThreadedWalk};
+use crate::{
+    SizeArgs, config::SizeUnit, dirsize::should_skip_path, output::OutputFormat, walk::ThreadedWalk,
+};
 
-use std::path::Path;
+use serde::Serialize;
+use std::{path::Path, process::ExitCode};
 
-fn should_skip_path(path: &Path) -> bool {
-    let path_str = path.to_string_lossy();
-    path_str.starts_with("/proc") || path_str.starts_with("/sys") || path_str.starts_with("/dev")
+#[derive(Serialize)]
+struct Total {
+    name: String,
+    path: String,
+    // Bytes
+    size: u64,
 }
 
-pub fn execute(args: &SizeArgs) {
-    if args.path.is_file() {
-        let metadata = args.path.metadata().expect("Failed to get metadata");
+pub fn execute(args: &SizeArgs, format: OutputFormat) -> ExitCode {
+    let metadata = match args.path.metadata() {
+        Ok(metadata) => metadata,
+        Err(e) => return cannot_access(&args.path, e),
+    };
 
-        println!(
-            "{} {}",
-            args.path
+    let total = if metadata.is_file() {
+        Total {
+            name: args
+                .path
                 .file_name()
                 .unwrap_or_default()
                 .to_string_lossy()
                 .to_string(),
-            SizeUnit::Auto.format_bytes(metadata.len())
-        );
-
-        return;
-    }
+            path: args.path.to_string_lossy().to_string(),
+            size: metadata.len(),
+        }
+    } else {
+        let canonical = match std::fs::canonicalize(&args.path) {
+            Ok(canonical) => canonical,
+            Err(e) => return cannot_access(&args.path, e),
+        };
 
-    let mut total = 0;
+        let mut total = 0;
 
-    for (path, _) in ThreadedWalk::new(&args.path)
-        .max_depth(args.depth)
-        .skip_hidden(!args.all)
-    {
-        if should_skip_path(&path) {
-            continue;
-        }
+        for (path, _) in ThreadedWalk::new(&args.path)
+            .max_depth(args.depth)
+            .skip_hidden(!args.all)
+            .ignore_files(!args.no_ignore)
+        {
+            if should_skip_path(&path) {
+                continue;
+            }
 
-        if path.is_file() {
-            let metadata = path.metadata().expect("Failed to get metadata");
-            if metadata.file_type().is_file() {
+            // Files that vanish during the walk don't count
+            if let Ok(metadata) = path.metadata()
+                && metadata.is_file()
+            {
                 total += metadata.len();
             }
         }
+
+        Total {
+            name: canonical
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            path: canonical.to_string_lossy().to_string(),
+            size: total,
+        }
+    };
+
+    if format != OutputFormat::Text {
+        _ = format.write(&[total], &mut std::io::stdout().lock());
+        return ExitCode::SUCCESS;
     }
 
-    println!(
-        "{} {}",
-        std::fs::canonicalize(&args.path)
-            .unwrap()
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string(),
-        SizeUnit::Auto.format_bytes(total)
-    )
+    println!("{} {}", total.name, SizeUnit::Auto.format_bytes(total.size));
+    ExitCode::SUCCESS
+}
+
+/// Like `list`, a bad operand is reported on stderr, whatever the output format
+fn cannot_access(path: &Path, e: std::io::Error) -> ExitCode {
+    eprintln!("pls: cannot access '{}': {}", path.display(), e);
+    ExitCode::from(2)
 }
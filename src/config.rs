@@ -1,7 +1,22 @@
+use crate::{
+    color,
+    dircolors::Dircolors,
+    expr::{Expr, Literal, Operand, Rhs},
+    icons::Icons,
+    theme::{Background, Theme},
+    util,
+};
+use chrono::{DateTime, Local};
 use figura::Value;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap, fmt::Display, hash::Hash, os::unix::fs::PermissionsExt, path::Path,
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
 };
 
 // ============================================================================
@@ -16,6 +31,13 @@ pub enum Op {
     Less,
     GreaterEqual,
     LessEqual,
+    Matches,
+    Glob,
+    Contains,
+    StartsWith,
+    EndsWith,
+    In,
+    Between,
 }
 
 impl<'de> Deserialize<'de> for Op {
@@ -31,13 +53,43 @@ impl<'de> Deserialize<'de> for Op {
             "<" | "lt" => Ok(Op::Less),
             ">=" | "gte" => Ok(Op::GreaterEqual),
             "<=" | "lte" => Ok(Op::LessEqual),
+            "=~" | "matches" => Ok(Op::Matches),
+            "glob" => Ok(Op::Glob),
+            "contains" => Ok(Op::Contains),
+            "starts_with" => Ok(Op::StartsWith),
+            "ends_with" => Ok(Op::EndsWith),
+            "in" => Ok(Op::In),
+            "between" => Ok(Op::Between),
             _ => Err(serde::de::Error::custom(format!("invalid operator: {}", s))),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op_str = match self {
+            Op::Equal => "==",
+            Op::NotEqual => "!=",
+            Op::Greater => ">",
+            Op::Less => "<",
+            Op::GreaterEqual => ">=",
+            Op::LessEqual => "<=",
+            Op::Matches => "matches",
+            Op::Glob => "glob",
+            Op::Contains => "contains",
+            Op::StartsWith => "starts_with",
+            Op::EndsWith => "ends_with",
+            Op::In => "in",
+            Op::Between => "between",
+        };
+
+        write!(f, "{}", op_str)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub enum TextStyle {
+    #[default]
     Normal,
     Bold,
     Italic,
@@ -51,12 +103,6 @@ pub enum TextStyle {
     DoubleUnderline,
 }
 
-impl Default for TextStyle {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 impl TextStyle {
     pub fn to_ansi(&self) -> &'static str {
         match self {
@@ -101,8 +147,9 @@ impl<'de> Deserialize<'de> for TextStyle {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SizeUnit {
+    #[default]
     Auto,
     Bytes,
     KB,
@@ -151,12 +198,6 @@ impl SizeUnit {
     }
 }
 
-impl Default for SizeUnit {
-    fn default() -> Self {
-        SizeUnit::Auto
-    }
-}
-
 impl<'de> Deserialize<'de> for SizeUnit {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -178,6 +219,93 @@ impl<'de> Deserialize<'de> for SizeUnit {
     }
 }
 
+/// How a timestamp is displayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeFormat {
+    // A chrono strftime pattern
+    Absolute(String),
+    // e.g. "3 minutes ago", "yesterday"
+    Relative,
+    // Relative if newer than `recent_days`, formatted with `format` otherwise
+    Hybrid { format: String, recent_days: u64 },
+}
+
+impl TimeFormat {
+    /// Like ls, which switches formats after six months
+    const RECENT_DAYS: u64 = 182;
+    const OLD_FORMAT: &str = "%b %d  %Y";
+
+    pub fn format(&self, t: Option<SystemTime>) -> String {
+        let Some(t) = t else {
+            return "N/A".to_string();
+        };
+
+        let now = SystemTime::now();
+
+        match self {
+            TimeFormat::Absolute(fmt) => DateTime::<Local>::from(t).format(fmt).to_string(),
+            TimeFormat::Relative => util::relative_time(t, now),
+            TimeFormat::Hybrid {
+                format,
+                recent_days,
+            } => {
+                let age = now.duration_since(t).unwrap_or_default();
+
+                if age.as_secs() < recent_days * 24 * 60 * 60 {
+                    util::relative_time(t, now)
+                } else {
+                    DateTime::<Local>::from(t).format(format).to_string()
+                }
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeFormat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Shorthand(String),
+            Full {
+                mode: String,
+                format: Option<String>,
+                recent_days: Option<u64>,
+            },
+        }
+
+        let (mode, format, recent_days) = match Raw::deserialize(deserializer)? {
+            Raw::Shorthand(s) => match s.as_str() {
+                "relative" | "hybrid" => (s, None, None),
+                _ => return Ok(TimeFormat::Absolute(s)),
+            },
+            Raw::Full {
+                mode,
+                format,
+                recent_days,
+            } => (mode, format, recent_days),
+        };
+
+        match mode.to_lowercase().as_str() {
+            "absolute" => Ok(TimeFormat::Absolute(format.ok_or_else(|| {
+                serde::de::Error::custom("absolute time format requires a format")
+            })?)),
+            "relative" => Ok(TimeFormat::Relative),
+            "hybrid" => Ok(TimeFormat::Hybrid {
+                format: format.unwrap_or_else(|| TimeFormat::OLD_FORMAT.to_string()),
+                recent_days: recent_days.unwrap_or(TimeFormat::RECENT_DAYS),
+            }),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid time format mode: {}",
+                mode
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FileKind {
     File,
@@ -186,6 +314,17 @@ pub enum FileKind {
     SymlinkDirectory,
     Executable,
     BrokenSymlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    // Regular files with the setuid / setgid bit
+    Setuid,
+    Setgid,
+    // Directories with the sticky bit (e.g. /tmp)
+    StickyDirectory,
+    // Directories writable by others, without the sticky bit
+    OtherWritable,
 }
 
 impl Display for FileKind {
@@ -197,12 +336,29 @@ impl Display for FileKind {
             FileKind::SymlinkDirectory => "symlink_directory",
             FileKind::Executable => "executable",
             FileKind::BrokenSymlink => "broken_symlink",
+            FileKind::Fifo => "fifo",
+            FileKind::Socket => "socket",
+            FileKind::BlockDevice => "block_device",
+            FileKind::CharDevice => "char_device",
+            FileKind::Setuid => "setuid",
+            FileKind::Setgid => "setgid",
+            FileKind::StickyDirectory => "sticky_directory",
+            FileKind::OtherWritable => "other_writable",
         };
 
         write!(f, "{}", kind_str)
     }
 }
 
+impl Serialize for FileKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl<'de> Deserialize<'de> for FileKind {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -217,6 +373,14 @@ impl<'de> Deserialize<'de> for FileKind {
             "symlink_directory" => Ok(FileKind::SymlinkDirectory),
             "executable" => Ok(FileKind::Executable),
             "broken_symlink" => Ok(FileKind::BrokenSymlink),
+            "fifo" => Ok(FileKind::Fifo),
+            "socket" => Ok(FileKind::Socket),
+            "block_device" => Ok(FileKind::BlockDevice),
+            "char_device" => Ok(FileKind::CharDevice),
+            "setuid" => Ok(FileKind::Setuid),
+            "setgid" => Ok(FileKind::Setgid),
+            "sticky_directory" => Ok(FileKind::StickyDirectory),
+            "other_writable" => Ok(FileKind::OtherWritable),
             _ => Err(serde::de::Error::custom(format!(
                 "Unknown file kind: {}",
                 s
@@ -226,28 +390,213 @@ impl<'de> Deserialize<'de> for FileKind {
 }
 
 impl FileKind {
-    pub fn from_path<P: AsRef<Path>>(path: P) -> (Self, std::fs::Metadata) {
-        let metadata = std::fs::symlink_metadata(&path).unwrap();
+    /// Fails if the entry can't be `lstat`ed, e.g. it vanished since it was listed
+    pub fn from_path<P: AsRef<Path>>(path: P) -> std::io::Result<(Self, std::fs::Metadata)> {
+        let metadata = std::fs::symlink_metadata(&path)?;
 
-        if metadata.file_type().is_symlink() {
+        let kind = if metadata.file_type().is_symlink() {
             // Try to follow the symlink - if it fails, the symlink is broken
             match std::fs::metadata(&path) {
                 Ok(target_metadata) => {
                     if target_metadata.is_dir() {
-                        (FileKind::SymlinkDirectory, metadata)
+                        FileKind::SymlinkDirectory
                     } else {
-                        (FileKind::SymlinkFile, metadata)
+                        FileKind::SymlinkFile
                     }
                 }
 
-                Err(_) => (FileKind::BrokenSymlink, metadata),
+                Err(_) => FileKind::BrokenSymlink,
+            }
+        } else {
+            Self::from_metadata(&metadata)
+        };
+
+        Ok((kind, metadata))
+    }
+
+    /// Classifies a non symlink entry,
+    /// with the same precedence as `dircolors`
+    fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let mode = metadata.mode();
+
+        if file_type.is_dir() {
+            if mode & 0o1000 != 0 {
+                FileKind::StickyDirectory
+            } else if mode & 0o002 != 0 {
+                FileKind::OtherWritable
+            } else {
+                FileKind::Directory
             }
-        } else if metadata.is_dir() {
-            (FileKind::Directory, metadata)
-        } else if metadata.permissions().mode() & 0o111 != 0 {
-            (FileKind::Executable, metadata)
+        } else if file_type.is_fifo() {
+            FileKind::Fifo
+        } else if file_type.is_socket() {
+            FileKind::Socket
+        } else if file_type.is_block_device() {
+            FileKind::BlockDevice
+        } else if file_type.is_char_device() {
+            FileKind::CharDevice
+        } else if mode & 0o4000 != 0 {
+            FileKind::Setuid
+        } else if mode & 0o2000 != 0 {
+            FileKind::Setgid
+        } else if mode & 0o111 != 0 {
+            FileKind::Executable
         } else {
-            (FileKind::File, metadata)
+            FileKind::File
+        }
+    }
+
+    /// Whether the entry is a directory, whatever its permission bits
+    pub fn is_dir(&self) -> bool {
+        matches!(
+            self,
+            FileKind::Directory | FileKind::StickyDirectory | FileKind::OtherWritable
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Modified,
+    Created,
+    Accessed,
+    Extension,
+    Kind,
+    Owner,
+    TotalSize,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(SortKey::Name),
+            "size" => Ok(SortKey::Size),
+            "modified" => Ok(SortKey::Modified),
+            "created" => Ok(SortKey::Created),
+            "accessed" => Ok(SortKey::Accessed),
+            "extension" => Ok(SortKey::Extension),
+            "kind" => Ok(SortKey::Kind),
+            "owner" => Ok(SortKey::Owner),
+            "total_size" => Ok(SortKey::TotalSize),
+            _ => Err(format!("invalid sort key: {}", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SortKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    // One row per entry, with the columns defined by `format`
+    #[default]
+    Table,
+    // Names packed in columns, filled top to bottom (ls -C)
+    Grid,
+    // Names packed in columns, filled left to right (ls -x)
+    Across,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(Layout::Table),
+            "grid" => Ok(Layout::Grid),
+            "across" => Ok(Layout::Across),
+            _ => Err(format!("invalid layout: {}", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Layout {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Where the styles of names come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StylesSource {
+    // Only the `styles` of the config
+    #[default]
+    Config,
+    // `LS_COLORS` replaces the `name` style
+    LsColors,
+    // `LS_COLORS` after the conditions of the `name` style
+    Merge,
+}
+
+impl<'de> Deserialize<'de> for StylesSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.to_lowercase().as_str() {
+            "config" => Ok(StylesSource::Config),
+            "ls_colors" => Ok(StylesSource::LsColors),
+            "merge" => Ok(StylesSource::Merge),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid styles source: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Coarse grouping of entries, used to list
+/// e.g. directories before everything else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryGroup {
+    Directories,
+    Files,
+    Symlinks,
+}
+
+impl EntryGroup {
+    pub fn of(kind: FileKind) -> Self {
+        match kind {
+            k if k.is_dir() => EntryGroup::Directories,
+            FileKind::SymlinkFile | FileKind::SymlinkDirectory | FileKind::BrokenSymlink => {
+                EntryGroup::Symlinks
+            }
+            _ => EntryGroup::Files,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EntryGroup {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.to_lowercase().as_str() {
+            "directories" => Ok(EntryGroup::Directories),
+            "files" => Ok(EntryGroup::Files),
+            "symlinks" => Ok(EntryGroup::Symlinks),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid entry group: {}",
+                s
+            ))),
         }
     }
 }
@@ -260,7 +609,7 @@ impl FileKind {
 #[serde(untagged)]
 pub enum Color {
     Named(String),   // "red" "blue" "green"
-    RGB(u8, u8, u8), // [255, 0, 0]
+    Rgb(u8, u8, u8), // [255, 0, 0]
     Hex(String),     // #FF5733
     Ansi(u8),        // ANSI 256 color code
 }
@@ -300,28 +649,30 @@ impl Color {
                     "bright magenta" => 65,
                     "bright cyan" => 66,
                     "bright white" => 67,
+                    // `#rrggbb` strings deserialize as names, untagged enums take the first match
+                    _ if name.starts_with('#') => {
+                        return Self::Hex(name.clone()).to_ansi_with_prefix(prefix);
+                    }
                     _ => return String::new(),
                 };
 
-                let base = if code >= 60 {
-                    if prefix == 48 { 100 - 60 } else { 90 - 60 }
-                } else {
-                    if prefix == 48 { 40 } else { 30 }
-                };
+                // Bright colors carry their +60 offset in the code itself
+                // (90-97 foreground, 100-107 background)
+                let base = if prefix == 48 { 40 } else { 30 };
 
                 format!("\x1b[{}m", base + code)
             }
-            Self::RGB(r, g, b) => format!("\x1b[{};2;{};{};{}m", prefix, r, g, b),
+            Self::Rgb(r, g, b) => format!("\x1b[{};2;{};{};{}m", prefix, r, g, b),
             Self::Hex(hex) => {
                 let hex = hex.trim_start_matches('#');
-                if hex.len() == 6 {
-                    if let (Ok(r), Ok(g), Ok(b)) = (
+                if hex.len() == 6
+                    && let (Ok(r), Ok(g), Ok(b)) = (
                         u8::from_str_radix(&hex[0..2], 16),
                         u8::from_str_radix(&hex[2..4], 16),
                         u8::from_str_radix(&hex[4..6], 16),
-                    ) {
-                        return format!("\x1b[{};2;{};{};{}m", prefix, r, g, b);
-                    }
+                    )
+                {
+                    return format!("\x1b[{};2;{};{};{}m", prefix, r, g, b);
                 }
                 String::new()
             }
@@ -338,6 +689,14 @@ pub struct Style {
     pub text: Option<Vec<TextStyle>>,
 }
 
+impl Style {
+    pub fn is_empty(&self) -> bool {
+        self.foreground.is_none()
+            && self.background.is_none()
+            && self.text.as_ref().is_none_or(|t| t.is_empty())
+    }
+}
+
 // ============================================================================
 // Apply Trait
 // ============================================================================
@@ -354,6 +713,10 @@ impl Apply for String {
 
 impl Apply for Style {
     fn apply(&self, s: Option<String>) -> String {
+        if !color::enabled() {
+            return s.unwrap_or_default();
+        }
+
         let mut out = String::new();
         let mut applied = false;
 
@@ -400,56 +763,82 @@ impl Apply for Style {
 // Generic Conditional Logic (Depends on Apply trait)
 // ============================================================================
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct Condition<T: Apply> {
-    pub variable: String,
-    pub op: Op,
-    pub value: String,
+    pub when: Expr,
     pub result: T,
 }
 
-impl<T: Apply> Condition<T> {
-    fn evaluate(&self, ctx: &HashMap<&'static str, Value>) -> bool {
-        let value = match ctx.get(self.variable.as_str()) {
-            Some(v) => v.to_string(),
-            None => return false,
-        };
+impl<'de, T: Apply + Deserialize<'de>> Deserialize<'de> for Condition<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        // A list for `in` and `between`
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawValue {
+            One(String),
+            List(Vec<String>),
+        }
 
-        match self.op {
-            Op::Equal => value == self.value,
-            Op::NotEqual => value != self.value,
-            Op::Greater => {
-                if let (Ok(a), Ok(b)) = (value.parse::<i64>(), self.value.parse::<i64>()) {
-                    a > b
-                } else {
-                    value > self.value
-                }
-            }
+        // Either `{ "when": "..." }` or `{ "variable", "op", "value" }`
+        #[derive(Deserialize)]
+        struct Raw<T> {
+            when: Option<String>,
+            variable: Option<String>,
+            op: Option<Op>,
+            value: Option<RawValue>,
+            result: T,
+        }
 
-            Op::Less => {
-                if let (Ok(a), Ok(b)) = (value.parse::<i64>(), self.value.parse::<i64>()) {
-                    a < b
-                } else {
-                    value < self.value
-                }
-            }
+        let raw: Raw<T> = Deserialize::deserialize(deserializer)?;
+
+        let when = match (raw.when, raw.variable, raw.op, raw.value) {
+            (Some(when), None, None, None) => Expr::parse(&when)
+                .map_err(|e| D::Error::custom(format!("invalid condition: {}", e)))?,
+            (None, Some(variable), Some(op), Some(value)) => {
+                // `"value": "10MiB"` is a size when comparing, but `==` stays textual,
+                // a directory may well be named `2024-01-15`
+                let ordering = matches!(
+                    op,
+                    Op::Greater | Op::Less | Op::GreaterEqual | Op::LessEqual | Op::Between
+                );
+
+                let literal = |s: String| {
+                    if ordering {
+                        Literal::typed(&s)
+                    } else {
+                        Literal::String(s)
+                    }
+                };
 
-            Op::GreaterEqual => {
-                if let (Ok(a), Ok(b)) = (value.parse::<i64>(), self.value.parse::<i64>()) {
-                    a >= b
-                } else {
-                    value >= self.value
-                }
-            }
+                let rhs = match value {
+                    RawValue::One(s) => Rhs::Operand(Operand::Literal(literal(s))),
+                    RawValue::List(list) => Rhs::List(list.into_iter().map(literal).collect()),
+                };
 
-            Op::LessEqual => {
-                if let (Ok(a), Ok(b)) = (value.parse::<i64>(), self.value.parse::<i64>()) {
-                    a <= b
-                } else {
-                    value <= self.value
-                }
+                Expr::binary(Operand::Variable(variable), op, rhs)
+                    .map_err(|e| D::Error::custom(format!("invalid condition: {}", e)))?
             }
-        }
+            (Some(_), ..) => {
+                return Err(D::Error::custom(
+                    "a condition has either `when` or `variable`, `op` and `value`",
+                ));
+            }
+            _ => {
+                return Err(D::Error::custom(
+                    "a condition needs `when`, or `variable`, `op` and `value`",
+                ));
+            }
+        };
+
+        Ok(Self {
+            when,
+            result: raw.result,
+        })
     }
 }
 
@@ -462,16 +851,12 @@ pub struct If<T: Apply> {
     conditions: Vec<Condition<T>>,
 }
 
+// Without conditions nor default, icons come from `icons`
 impl Default for If<String> {
     fn default() -> Self {
         Self {
-            default: Some(String::from("f")),
-            conditions: vec![Condition {
-                variable: String::from("kind"),
-                op: Op::Equal,
-                value: String::from("directory"),
-                result: "d".to_string(),
-            }],
+            default: None,
+            conditions: Vec::new(),
         }
     }
 }
@@ -488,7 +873,7 @@ impl Default for If<Style> {
 impl<T: Apply> If<T> {
     pub fn resolve(&self, s: Option<String>, ctx: &HashMap<&'static str, Value>) -> String {
         for rule in &self.conditions {
-            if rule.evaluate(ctx) {
+            if rule.when.evaluate(ctx) {
                 return rule.result.apply(s);
             }
         }
@@ -499,12 +884,53 @@ impl<T: Apply> If<T> {
 
         s.unwrap_or_default()
     }
+
+    /// The result of the first condition that holds
+    pub fn matching(&self, ctx: &HashMap<&'static str, Value>) -> Option<&T> {
+        self.conditions
+            .iter()
+            .find(|rule| rule.when.evaluate(ctx))
+            .map(|rule| &rule.result)
+    }
+
+    pub fn default_result(&self) -> Option<&T> {
+        self.default.as_ref()
+    }
+
+    /// Whether any condition reads the variable
+    pub fn uses(&self, variable: &str) -> bool {
+        self.conditions.iter().any(|c| c.when.uses(variable))
+    }
 }
 
 // ============================================================================
 // Configuration Structures (Depends on everything above)
 // ============================================================================
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TreeConfig {
+    pub branch: String,
+    pub last: String,
+    pub vertical: String,
+    pub blank: String,
+    pub collapse: bool,
+    pub max_entries: usize,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            branch: String::from("├── "),
+            last: String::from("└── "),
+            vertical: String::from("│   "),
+            blank: String::from("    "),
+            collapse: true,
+            max_entries: 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct ListConfig {
@@ -512,11 +938,30 @@ pub struct ListConfig {
     pub padding: usize,
     pub headers: Vec<String>,
     pub icon: If<String>,
+    pub icons: Icons,
     pub styles: HashMap<String, If<Style>>,
     pub size_unit: SizeUnit,
-    pub created_fmt: String,
-    pub modified_fmt: String,
-    pub accessed_fmt: String,
+    pub created_fmt: TimeFormat,
+    pub modified_fmt: TimeFormat,
+    pub accessed_fmt: TimeFormat,
+    pub sort: Vec<SortKey>,
+    pub reverse: bool,
+    pub group_order: Vec<EntryGroup>,
+    pub tree: TreeConfig,
+    pub layout: Layout,
+    pub grid_format: String,
+    pub git_header: bool,
+    pub ignore_files: bool,
+    pub show_ignored: bool,
+    // Put between the name and the target by `{link}`
+    pub link_arrow: String,
+    pub styles_source: StylesSource,
+    // A dircolors file, instead of `LS_COLORS`
+    pub dircolors: Option<PathBuf>,
+    // Built-in or `themes/<name>.json`, under `styles`
+    pub theme: Option<String>,
+    // Picks the variant of themes that have one
+    pub background: Background,
 }
 
 impl Default for ListConfig {
@@ -532,12 +977,111 @@ impl Default for ListConfig {
             padding: 2,
             headers: vec![],
             icon: If::<String>::default(),
+            icons: Icons::default(),
             styles: HashMap::new(),
             size_unit: SizeUnit::Auto,
-            created_fmt: String::from("%b %d %H:%M"),
-            modified_fmt: String::from("%b %d %H:%M"),
-            accessed_fmt: String::from("%b %d %H:%M"),
+            created_fmt: TimeFormat::Absolute(String::from("%b %d %H:%M")),
+            modified_fmt: TimeFormat::Absolute(String::from("%b %d %H:%M")),
+            accessed_fmt: TimeFormat::Absolute(String::from("%b %d %H:%M")),
+            sort: vec![SortKey::Name],
+            reverse: false,
+            group_order: vec![EntryGroup::Directories],
+            tree: TreeConfig::default(),
+            layout: Layout::Table,
+            grid_format: String::from("{icon} {name}"),
+            git_header: true,
+            ignore_files: true,
+            show_ignored: false,
+            link_arrow: String::from(" -> "),
+            styles_source: StylesSource::Config,
+            dircolors: None,
+            theme: None,
+            background: Background::Auto,
+        }
+    }
+}
+
+impl ListConfig {
+    /// Puts the theme under the configured styles: for the same key,
+    /// the configured conditions are checked first and its default wins if set
+    fn load_theme(&mut self) -> Result<(), String> {
+        let Some(name) = &self.theme else {
+            return Ok(());
+        };
+
+        let theme = Theme::load(name)?;
+
+        // Without colors, the terminal isn't worth asking
+        let background = if theme.has_variants() && color::enabled() {
+            self.background.resolve()
+        } else {
+            self.background
+        };
+
+        for (key, mut style) in theme.into_styles(background) {
+            if let Some(user) = self.styles.remove(&key) {
+                let mut conditions = user.conditions;
+                conditions.append(&mut style.conditions);
+                style.conditions = conditions;
+
+                if user.default.as_ref().is_some_and(|d| !d.is_empty()) {
+                    style.default = user.default;
+                }
+            }
+
+            self.styles.insert(key, style);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the `LS_COLORS` (or dircolors) conditions to the `name` style,
+    /// as `styles_source` says. Nothing changes without a database.
+    fn load_dircolors(&mut self) -> Result<(), String> {
+        if self.styles_source == StylesSource::Config {
+            return Ok(());
+        }
+
+        let dircolors = match &self.dircolors {
+            Some(path) => Dircolors::from_file(path)
+                .map_err(|e| format!("cannot read dircolors file '{}': {}", path.display(), e))?,
+            None => match Dircolors::from_env() {
+                Some(dircolors) => dircolors,
+                None => return Ok(()),
+            },
+        };
+
+        let conditions = dircolors.conditions();
+
+        match self.styles_source {
+            StylesSource::LsColors => {
+                self.styles.insert(
+                    String::from("name"),
+                    If {
+                        default: None,
+                        conditions,
+                    },
+                );
+            }
+            StylesSource::Merge => self
+                .styles
+                .entry(String::from("name"))
+                .or_default()
+                .conditions
+                .extend(conditions),
+            StylesSource::Config => {}
         }
+
+        Ok(())
+    }
+
+    /// Whether the variable is displayed or read by a condition,
+    /// used to skip computing expensive variables
+    pub fn uses(&self, variable: &str) -> bool {
+        util::template_variables(&self.format).contains(variable)
+            || util::template_variables(&[&self.grid_format]).contains(variable)
+            || self.icon.uses(variable)
+            || self.styles.values().any(|s| s.uses(variable))
     }
 }
 
@@ -551,7 +1095,8 @@ impl Config {
     const SCHEMA: &str = include_str!("../config.schema.json");
     const DEFAULT: &str = include_str!("../config.default.json");
 
-    pub fn parse() -> Result<Self, Box<dyn std::error::Error>> {
+    /// Reads the user config, `theme` overrides the configured one
+    pub fn parse(theme: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
         let config_dir = dirs::config_dir()
             .ok_or("Could not determine config directory")?
             .join("pls");
@@ -570,7 +1115,14 @@ impl Config {
         }
 
         let config_content = std::fs::read_to_string(&config_file)?;
-        let config: Config = serde_json::from_str(&config_content)?;
+        let mut config: Config = serde_json::from_str(&config_content)?;
+
+        if let Some(theme) = theme {
+            config.ls.theme = Some(theme.to_string());
+        }
+
+        config.ls.load_theme()?;
+        config.ls.load_dircolors()?;
 
         Ok(config)
     }
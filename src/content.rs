@@ -0,0 +1,294 @@
+use crate::mime;
+use serde::Serialize;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::Path,
+};
+
+const CHUNK_LEN: usize = 64 * 1024;
+/// Bigger files are only read this far, so that a huge file doesn't stall the listing
+const READ_LIMIT: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Encoding {
+    #[serde(rename = "UTF-8")]
+    Utf8,
+    #[serde(rename = "UTF-16LE")]
+    Utf16Le,
+    #[serde(rename = "UTF-16BE")]
+    Utf16Be,
+    #[serde(rename = "Latin-1")]
+    Latin1,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encoding_str = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        };
+
+        write!(f, "{}", encoding_str)
+    }
+}
+
+/// Line endings used by a text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Eol {
+    #[serde(rename = "LF")]
+    Lf,
+    #[serde(rename = "CRLF")]
+    Crlf,
+    #[serde(rename = "CR")]
+    Cr,
+    #[serde(rename = "mixed")]
+    Mixed,
+}
+
+impl Display for Eol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let eol_str = match self {
+            Eol::Lf => "LF",
+            Eol::Crlf => "CRLF",
+            Eol::Cr => "CR",
+            Eol::Mixed => "mixed",
+        };
+
+        write!(f, "{}", eol_str)
+    }
+}
+
+/// What the content of a text file looks like
+#[derive(Debug, Clone)]
+pub struct TextStats {
+    // A last line without a newline counts too, like editors
+    pub lines: u64,
+    // Runs of non whitespace characters, like `wc -w`
+    pub words: u64,
+    pub encoding: Encoding,
+    // `None` if there is no line break at all
+    pub eol: Option<Eol>,
+    pub trailing_newline: bool,
+    // Only the first `READ_LIMIT` bytes were read,
+    // the counts are partial and the trailing newline unknown
+    pub truncated: bool,
+}
+
+/// Content of a regular file
+#[derive(Debug, Clone)]
+pub enum Content {
+    Text(TextStats),
+    Binary,
+}
+
+/// Counts lines, words and line endings one code unit at a time,
+/// so that UTF-16 goes through the same path as the 8-bit encodings
+#[derive(Default)]
+struct Counter {
+    newlines: u64,
+    words: u64,
+    lf: u64,
+    crlf: u64,
+    cr: u64,
+    in_word: bool,
+    // Last unit seen, a CR is only known to be alone at the next one
+    last: Option<u32>,
+}
+
+impl Counter {
+    fn feed(&mut self, unit: u32) {
+        match unit {
+            0x0a if self.last == Some(0x0d) => {
+                self.crlf += 1;
+                self.cr -= 1;
+            }
+            0x0a => {
+                self.newlines += 1;
+                self.lf += 1;
+            }
+            0x0d => {
+                self.newlines += 1;
+                self.cr += 1;
+            }
+            _ => {}
+        }
+
+        let space = matches!(unit, 0x09..=0x0d | 0x20);
+
+        if !space && !self.in_word {
+            self.words += 1;
+        }
+
+        self.in_word = !space;
+        self.last = Some(unit);
+    }
+
+    fn finish(self, encoding: Encoding, truncated: bool) -> TextStats {
+        let trailing_newline = matches!(self.last, Some(0x0a | 0x0d));
+
+        let kinds = [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&n| n > 0)
+            .count();
+
+        let eol = match kinds {
+            0 => None,
+            1 if self.lf > 0 => Some(Eol::Lf),
+            1 if self.crlf > 0 => Some(Eol::Crlf),
+            1 => Some(Eol::Cr),
+            _ => Some(Eol::Mixed),
+        };
+
+        let lines = match self.last {
+            Some(_) if !trailing_newline => self.newlines + 1,
+            _ => self.newlines,
+        };
+
+        TextStats {
+            lines,
+            words: self.words,
+            encoding,
+            eol,
+            trailing_newline: trailing_newline && !truncated,
+            truncated,
+        }
+    }
+}
+
+impl Content {
+    /// Reads a regular file (not following symlinks) to the end, or up to `READ_LIMIT`,
+    /// unless its first bytes or a NUL byte show it is binary.
+    ///
+    /// UTF-16 is only recognized by its byte order mark,
+    /// anything that isn't valid UTF-8 is assumed to be Latin-1.
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+
+        if !path.symlink_metadata().ok()?.is_file() {
+            return None;
+        }
+
+        let mut file = File::open(path).ok()?;
+        let mut buf = vec![0; CHUNK_LEN];
+        let mut counter = Counter::default();
+        let mut encoding = None;
+        let mut utf8 = true;
+        // Bytes of a character (or UTF-16 unit) split between chunks
+        let mut carry: Vec<u8> = Vec::new();
+        let mut read = 0;
+
+        let truncated = loop {
+            // A file of exactly `READ_LIMIT` bytes was read whole
+            if read >= READ_LIMIT {
+                break read > READ_LIMIT || has_more(&mut file);
+            }
+
+            let n = match file.read(&mut buf) {
+                Ok(0) => break false,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            };
+
+            read += n as u64;
+            let mut chunk = &buf[..n];
+
+            if encoding.is_none() {
+                // Same guess as the content type, for files without NUL bytes
+                if !mime::is_text(chunk) {
+                    return Some(Self::Binary);
+                }
+
+                let (detected, bom) = if chunk.starts_with(b"\xff\xfe") {
+                    (Encoding::Utf16Le, 2)
+                } else if chunk.starts_with(b"\xfe\xff") {
+                    (Encoding::Utf16Be, 2)
+                } else if chunk.starts_with(b"\xef\xbb\xbf") {
+                    (Encoding::Utf8, 3)
+                } else {
+                    (Encoding::Utf8, 0)
+                };
+
+                encoding = Some(detected);
+                chunk = &chunk[bom..];
+            }
+
+            match encoding {
+                Some(Encoding::Utf16Le) | Some(Encoding::Utf16Be) => {
+                    let little = encoding == Some(Encoding::Utf16Le);
+
+                    carry.extend_from_slice(chunk);
+
+                    let pairs = carry.chunks_exact(2);
+                    let rest = pairs.remainder().to_vec();
+
+                    for pair in pairs {
+                        let unit = if little {
+                            u16::from_le_bytes([pair[0], pair[1]])
+                        } else {
+                            u16::from_be_bytes([pair[0], pair[1]])
+                        };
+
+                        counter.feed(unit as u32);
+                    }
+
+                    carry = rest;
+                }
+                _ => {
+                    if chunk.contains(&0) {
+                        return Some(Self::Binary);
+                    }
+
+                    if utf8 {
+                        carry.extend_from_slice(chunk);
+
+                        match std::str::from_utf8(&carry) {
+                            Ok(_) => carry.clear(),
+                            // Incomplete character at the end of the chunk
+                            Err(e) if e.error_len().is_none() => {
+                                carry.drain(..e.valid_up_to());
+                            }
+                            Err(_) => {
+                                utf8 = false;
+                                carry.clear();
+                            }
+                        }
+                    }
+
+                    for &b in chunk {
+                        counter.feed(b as u32);
+                    }
+                }
+            }
+        };
+
+        // A character cut by the limit is not an error
+        let incomplete = !carry.is_empty() && !truncated;
+
+        let encoding = match encoding {
+            Some(Encoding::Utf8) | None if !utf8 || incomplete => Encoding::Latin1,
+            Some(encoding) => encoding,
+            None => Encoding::Utf8,
+        };
+
+        Some(Self::Text(counter.finish(encoding, truncated)))
+    }
+}
+
+/// Whether at least one more byte can be read
+fn has_more(file: &mut File) -> bool {
+    let mut byte = [0; 1];
+
+    loop {
+        match file.read(&mut byte) {
+            Ok(n) => return n > 0,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            // Can't tell, so the counts can't be trusted
+            Err(_) => return true,
+        }
+    }
+}
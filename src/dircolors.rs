@@ -0,0 +1,447 @@
+use crate::{
+    config::{Color, Condition, FileKind, Op, Style, TextStyle},
+    expr::{Expr, Literal, Operand, Rhs},
+};
+use std::path::{Path, PathBuf};
+
+/// Keywords of dircolors files and their `LS_COLORS` keys
+const KEYWORDS: [(&str, &str); 26] = [
+    ("NORMAL", "no"),
+    ("NORM", "no"),
+    ("FILE", "fi"),
+    ("RESET", "rs"),
+    ("DIR", "di"),
+    ("LINK", "ln"),
+    ("LNK", "ln"),
+    ("SYMLINK", "ln"),
+    ("MULTIHARDLINK", "mh"),
+    ("FIFO", "pi"),
+    ("PIPE", "pi"),
+    ("SOCK", "so"),
+    ("DOOR", "do"),
+    ("BLK", "bd"),
+    ("BLOCK", "bd"),
+    ("CHR", "cd"),
+    ("CHAR", "cd"),
+    ("ORPHAN", "or"),
+    ("MISSING", "mi"),
+    ("SETUID", "su"),
+    ("SETGID", "sg"),
+    ("CAPABILITY", "ca"),
+    ("STICKY_OTHER_WRITABLE", "tw"),
+    ("OTHER_WRITABLE", "ow"),
+    ("STICKY", "st"),
+    ("EXEC", "ex"),
+];
+
+/// Where a database is looked for when `LS_COLORS` isn't set
+const DIRCOLORS_FILES: [&str; 2] = [".dircolors", ".dir_colors"];
+
+/// A color database, in `LS_COLORS` order
+#[derive(Debug, Clone, Default)]
+pub struct Dircolors {
+    // (key, SGR sequence), e.g. ("di", "01;34") or ("*.tar", "01;31")
+    entries: Vec<(String, String)>,
+}
+
+impl Dircolors {
+    /// Parses `di=01;34:ln=01;36:*.tar=01;31:...`
+    pub fn from_ls_colors(s: &str) -> Self {
+        let entries = s
+            .split(':')
+            .filter_map(|e| e.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Parses a dircolors database (`dircolors -p`).
+    /// `TERM` sections are not checked, every entry applies.
+    pub fn from_dircolors(s: &str) -> Self {
+        let mut entries = Vec::new();
+
+        for line in s.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            let Some((keyword, value)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+
+            let value = value.trim().to_string();
+
+            let key = if keyword.starts_with('.') {
+                format!("*{}", keyword)
+            } else if keyword.starts_with('*') {
+                keyword.to_string()
+            } else if let Some((_, key)) = KEYWORDS
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(keyword))
+            {
+                key.to_string()
+            } else {
+                // TERM, COLOR, OPTIONS, EIGHTBIT...
+                continue;
+            };
+
+            entries.push((key, value));
+        }
+
+        Self { entries }
+    }
+
+    /// `LS_COLORS`, or the first dircolors file found in the home directory
+    pub fn from_env() -> Option<Self> {
+        if let Ok(ls_colors) = std::env::var("LS_COLORS")
+            && !ls_colors.is_empty()
+        {
+            return Some(Self::from_ls_colors(&ls_colors));
+        }
+
+        let home = dirs::home_dir()?;
+
+        DIRCOLORS_FILES
+            .iter()
+            .map(|f| home.join(f))
+            .chain([PathBuf::from("/etc/DIR_COLORS")])
+            .find_map(|p| Self::from_file(&p).ok())
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::from_dircolors(&std::fs::read_to_string(path)?))
+    }
+
+    /// The style of a key, the last one set wins.
+    /// `ln=target` has none, links are styled like their target.
+    fn get(&self, key: &str) -> Option<Style> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .filter(|(_, sgr)| sgr != "target")
+            .map(|(_, sgr)| style_from_sgr(sgr))
+            .filter(|s| !s.is_empty())
+    }
+
+    /// The database as conditions on the `name` style,
+    /// in the order `ls` checks them
+    pub fn conditions(&self) -> Vec<Condition<Style>> {
+        let kind = |kind: FileKind| Expr::equal("kind", &kind.to_string());
+        let parse = |s: &str| Expr::parse(s).expect("built-in condition");
+
+        let rules: Vec<(&str, Expr)> = vec![
+            (
+                "tw",
+                parse("kind == 'sticky_directory' && other_perms matches '^.w'"),
+            ),
+            ("st", kind(FileKind::StickyDirectory)),
+            ("ow", kind(FileKind::OtherWritable)),
+            ("di", kind(FileKind::Directory)),
+            ("di", kind(FileKind::StickyDirectory)),
+            ("di", kind(FileKind::OtherWritable)),
+            ("ln", kind(FileKind::SymlinkFile)),
+            ("ln", kind(FileKind::SymlinkDirectory)),
+            ("or", kind(FileKind::BrokenSymlink)),
+            ("pi", kind(FileKind::Fifo)),
+            ("so", kind(FileKind::Socket)),
+            ("bd", kind(FileKind::BlockDevice)),
+            ("cd", kind(FileKind::CharDevice)),
+            ("su", kind(FileKind::Setuid)),
+            ("sg", kind(FileKind::Setgid)),
+            ("ca", parse("capabilities != ''")),
+            ("ex", kind(FileKind::Executable)),
+            ("mh", parse("kind == 'file' && nlink > 1")),
+        ];
+
+        let mut conditions: Vec<Condition<Style>> = rules
+            .into_iter()
+            .filter_map(|(key, when)| self.get(key).map(|result| Condition { when, result }))
+            .collect();
+
+        // Like `ls`, suffixes only apply to regular files, the last one set wins
+        for (key, sgr) in self.entries.iter().rev() {
+            let Some(suffix) = key.strip_prefix('*') else {
+                continue;
+            };
+
+            let result = style_from_sgr(sgr);
+
+            if result.is_empty() {
+                continue;
+            }
+
+            let ends_with = Expr::binary(
+                Operand::Variable(String::from("name")),
+                Op::EndsWith,
+                Rhs::Operand(Operand::Literal(Literal::String(suffix.to_string()))),
+            )
+            .expect("ends_with takes a string");
+
+            conditions.push(Condition {
+                when: Expr::And(Box::new(kind(FileKind::File)), Box::new(ends_with)),
+                result,
+            });
+        }
+
+        for key in ["fi", "no"] {
+            if let Some(result) = self.get(key) {
+                conditions.push(Condition {
+                    when: kind(FileKind::File),
+                    result,
+                });
+            }
+        }
+
+        conditions
+    }
+}
+
+/// Translates an SGR sequence like `01;38;5;208` into a style,
+/// codes without an equivalent are dropped
+pub fn style_from_sgr(sgr: &str) -> Style {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    let mut style = Style::default();
+    let mut text = Vec::new();
+    let codes: Vec<u16> = sgr.split(';').filter_map(|c| c.parse().ok()).collect();
+    let mut i = 0;
+
+    // `38;5;n` or `38;2;r;g;b`, `i` is on the 38 / 48
+    let extended = |i: &mut usize| -> Option<Color> {
+        let (len, color) = match codes.get(*i + 1) {
+            Some(5) => (2, codes.get(*i + 2).map(|&n| Color::Ansi(n as u8))),
+            Some(2) => (
+                4,
+                match codes.get(*i + 2..*i + 5) {
+                    Some(&[r, g, b]) => Some(Color::Rgb(r as u8, g as u8, b as u8)),
+                    _ => None,
+                },
+            ),
+            _ => return None,
+        };
+
+        // Even if incomplete, the parameters are not codes of their own
+        *i += len;
+        color
+    };
+
+    while i < codes.len() {
+        match codes[i] {
+            0 => {
+                style = Style::default();
+                text.clear();
+            }
+            1 => text.push(TextStyle::Bold),
+            2 => text.push(TextStyle::Dim),
+            3 => text.push(TextStyle::Italic),
+            4 => text.push(TextStyle::Underline),
+            5 | 6 => text.push(TextStyle::Blink),
+            7 => text.push(TextStyle::Inverse),
+            8 => text.push(TextStyle::Conceal),
+            9 => text.push(TextStyle::Strikethrough),
+            21 => text.push(TextStyle::DoubleUnderline),
+            c @ 30..=37 => style.foreground = Some(Color::Named(NAMES[(c - 30) as usize].into())),
+            c @ 40..=47 => style.background = Some(Color::Named(NAMES[(c - 40) as usize].into())),
+            c @ 90..=97 => {
+                let name = format!("bright {}", NAMES[(c - 90) as usize]);
+                style.foreground = Some(Color::Named(name));
+            }
+            c @ 100..=107 => {
+                let name = format!("bright {}", NAMES[(c - 100) as usize]);
+                style.background = Some(Color::Named(name));
+            }
+            38 => style.foreground = extended(&mut i),
+            48 => style.background = extended(&mut i),
+            39 => style.foreground = None,
+            49 => style.background = None,
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    if !text.is_empty() {
+        style.text = Some(text);
+    }
+
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figura::Value;
+    use std::collections::HashMap;
+
+    /// The result of the first condition that holds, like the `name` style
+    fn first<'a>(
+        conditions: &'a [Condition<Style>],
+        vars: &[(&'static str, &str)],
+    ) -> Option<&'a Style> {
+        let ctx: HashMap<&'static str, Value> = vars
+            .iter()
+            .map(|(k, v)| (*k, Value::String(v.to_string())))
+            .collect();
+
+        conditions
+            .iter()
+            .find(|c| c.when.evaluate(&ctx))
+            .map(|c| &c.result)
+    }
+
+    fn foreground(style: Option<&Style>) -> Option<String> {
+        match style?.foreground.as_ref()? {
+            Color::Named(name) => Some(name.clone()),
+            color => Some(format!("{:?}", color)),
+        }
+    }
+
+    #[test]
+    fn sgr_basic_colors() {
+        let style = style_from_sgr("01;31");
+        assert!(matches!(style.foreground, Some(Color::Named(ref n)) if n == "red"));
+        assert!(matches!(style.text.as_deref(), Some([TextStyle::Bold])));
+
+        let style = style_from_sgr("94;42");
+        assert!(matches!(style.foreground, Some(Color::Named(ref n)) if n == "bright blue"));
+        assert!(matches!(style.background, Some(Color::Named(ref n)) if n == "green"));
+    }
+
+    #[test]
+    fn sgr_256_colors() {
+        let style = style_from_sgr("01;38;5;208");
+        assert!(matches!(style.foreground, Some(Color::Ansi(208))));
+        assert!(matches!(style.text.as_deref(), Some([TextStyle::Bold])));
+
+        let style = style_from_sgr("48;5;17;4");
+        assert!(matches!(style.background, Some(Color::Ansi(17))));
+        assert!(matches!(
+            style.text.as_deref(),
+            Some([TextStyle::Underline])
+        ));
+    }
+
+    #[test]
+    fn sgr_true_colors() {
+        let style = style_from_sgr("38;2;255;128;0;3");
+        assert!(matches!(style.foreground, Some(Color::Rgb(255, 128, 0))));
+        assert!(matches!(style.text.as_deref(), Some([TextStyle::Italic])));
+
+        let style = style_from_sgr("48;2;1;2;3");
+        assert!(matches!(style.background, Some(Color::Rgb(1, 2, 3))));
+    }
+
+    #[test]
+    fn sgr_resets_and_incomplete_sequences() {
+        assert!(style_from_sgr("01;31;0").is_empty());
+        assert!(style_from_sgr("31;39").foreground.is_none());
+        assert!(style_from_sgr("38;5").is_empty());
+        assert!(style_from_sgr("38;2;1;2").is_empty());
+        assert!(style_from_sgr("").is_empty());
+    }
+
+    #[test]
+    fn ls_colors_entries() {
+        let db = Dircolors::from_ls_colors("di=01;34:*.tar=01;31:broken:ln=target:");
+
+        assert_eq!(
+            db.entries,
+            [
+                ("di".to_string(), "01;34".to_string()),
+                ("*.tar".to_string(), "01;31".to_string()),
+                ("ln".to_string(), "target".to_string()),
+            ]
+        );
+
+        // Links are styled like their target
+        assert!(db.get("ln").is_none());
+        assert!(db.get("di").is_some());
+    }
+
+    #[test]
+    fn dircolors_keywords_and_extensions() {
+        let db = Dircolors::from_dircolors(
+            "# comment\n\
+             TERM xterm*\n\
+             COLOR tty\n\
+             dir 01;34 # directories\n\
+             SYMLINK 01;36\n\
+             .tar 01;31\n\
+             *README 33\n\
+             STICKY_OTHER_WRITABLE 30;42\n",
+        );
+
+        let keys: Vec<&str> = db.entries.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["di", "ln", "*.tar", "*README", "tw"]);
+        assert_eq!(db.entries[0].1, "01;34");
+    }
+
+    #[test]
+    fn conditions_follow_ls_order() {
+        let db =
+            Dircolors::from_ls_colors("fi=37:di=34:tw=30;42:st=35:ex=32:mh=36:*.tar=31:*.sh=33");
+        let conditions = db.conditions();
+
+        let sticky = [("kind", "sticky_directory"), ("other_perms", "rwt")];
+        assert_eq!(
+            foreground(first(&conditions, &sticky)).as_deref(),
+            Some("black")
+        );
+
+        let sticky = [("kind", "sticky_directory"), ("other_perms", "r-t")];
+        assert_eq!(
+            foreground(first(&conditions, &sticky)).as_deref(),
+            Some("magenta")
+        );
+
+        let dir = [("kind", "directory"), ("name", "x.tar")];
+        assert_eq!(
+            foreground(first(&conditions, &dir)).as_deref(),
+            Some("blue")
+        );
+
+        // Suffixes only apply to regular files
+        let script = [("kind", "executable"), ("name", "run.sh")];
+        assert_eq!(
+            foreground(first(&conditions, &script)).as_deref(),
+            Some("green")
+        );
+
+        let archive = [("kind", "file"), ("name", "a.tar"), ("nlink", "1")];
+        assert_eq!(
+            foreground(first(&conditions, &archive)).as_deref(),
+            Some("red")
+        );
+
+        let linked = [("kind", "file"), ("name", "a.tar"), ("nlink", "2")];
+        assert_eq!(
+            foreground(first(&conditions, &linked)).as_deref(),
+            Some("cyan")
+        );
+
+        let plain = [("kind", "file"), ("name", "notes"), ("nlink", "1")];
+        assert_eq!(
+            foreground(first(&conditions, &plain)).as_deref(),
+            Some("white")
+        );
+    }
+
+    #[test]
+    fn last_extension_wins() {
+        let conditions = Dircolors::from_ls_colors("*.gz=31:*.gz=32").conditions();
+
+        let file = [("kind", "file"), ("name", "a.gz")];
+        assert_eq!(
+            foreground(first(&conditions, &file)).as_deref(),
+            Some("green")
+        );
+    }
+
+    #[test]
+    fn empty_styles_are_skipped() {
+        let conditions = Dircolors::from_ls_colors("di=0:ln=target:*.tar=").conditions();
+        assert!(conditions.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::walk::ThreadedWalk;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Virtual filesystems, whose sizes are meaningless
+const VIRTUAL_ROOTS: [&str; 3] = ["/proc", "/sys", "/dev"];
+
+/// Whether the path is inside a virtual filesystem,
+/// comparing whole components so `/development` is not skipped
+pub fn should_skip_path(path: &Path) -> bool {
+    let absolute = if path.is_absolute() {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+    };
+
+    VIRTUAL_ROOTS.iter().any(|root| absolute.starts_with(root))
+}
+
+/// Computes recursive directory sizes, caching the size of
+/// every subdirectory met along the way, so listing `a` and `a/b`
+/// only walks `a` once.
+#[derive(Debug)]
+pub struct DirSizes {
+    cache: Mutex<HashMap<PathBuf, u64>>,
+    skip_hidden: bool,
+    ignore_files: bool,
+}
+
+impl DirSizes {
+    pub fn new(skip_hidden: bool, ignore_files: bool) -> Self {
+        Self {
+            cache: Mutex::new(HashMap::new()),
+            skip_hidden,
+            ignore_files,
+        }
+    }
+
+    /// Total size of the regular files below `dir`, in bytes.
+    ///
+    /// Must not be called from a rayon worker: it blocks on
+    /// the channel of a `ThreadedWalk`, which runs on the same pool.
+    pub fn get<P: AsRef<Path>>(&self, dir: P) -> u64 {
+        let dir = dir.as_ref();
+
+        if let Some(size) = self.cache.lock().unwrap().get(dir) {
+            return *size;
+        }
+
+        let totals = self.walk(dir);
+        let size = totals.get(dir).copied().unwrap_or_default();
+
+        self.cache.lock().unwrap().extend(totals);
+
+        size
+    }
+
+    fn walk(&self, root: &Path) -> HashMap<PathBuf, u64> {
+        let mut totals: HashMap<PathBuf, u64> = HashMap::new();
+
+        for (path, _) in ThreadedWalk::new(root)
+            .skip_hidden(self.skip_hidden)
+            .ignore_files(self.ignore_files)
+        {
+            if should_skip_path(&path) {
+                continue;
+            }
+
+            let Ok(metadata) = path.symlink_metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                // So that empty directories are cached too
+                totals.entry(path).or_default();
+                continue;
+            }
+
+            if !metadata.is_file() {
+                continue;
+            }
+
+            for ancestor in path.ancestors().skip(1) {
+                match totals.get_mut(ancestor) {
+                    Some(total) => *total += metadata.len(),
+                    None => {
+                        totals.insert(ancestor.to_path_buf(), metadata.len());
+                    }
+                }
+
+                if ancestor == root {
+                    break;
+                }
+            }
+        }
+
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_virtual_filesystems() {
+        assert!(should_skip_path(Path::new("/proc")));
+        assert!(should_skip_path(Path::new("/proc/1/status")));
+        assert!(should_skip_path(Path::new("/sys/class")));
+        assert!(should_skip_path(Path::new("/dev/null")));
+    }
+
+    #[test]
+    fn compares_whole_components() {
+        assert!(!should_skip_path(Path::new("/development/app")));
+        assert!(!should_skip_path(Path::new("/system-backup")));
+        assert!(!should_skip_path(Path::new("/devices")));
+        assert!(!should_skip_path(Path::new("/home/proc")));
+    }
+}
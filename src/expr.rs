@@ -0,0 +1,988 @@
+use crate::config::Op;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use figura::Value;
+use regex::Regex;
+use std::{collections::HashMap, fmt::Display, time::SystemTime};
+
+/// Powers of 1024, like the sizes in the listing
+const SIZE_UNITS: [(&str, u64); 16] = [
+    ("B", 1),
+    ("K", 1 << 10),
+    ("KB", 1 << 10),
+    ("kB", 1 << 10),
+    ("KiB", 1 << 10),
+    ("M", 1 << 20),
+    ("MB", 1 << 20),
+    ("MiB", 1 << 20),
+    ("G", 1 << 30),
+    ("GB", 1 << 30),
+    ("GiB", 1 << 30),
+    ("T", 1 << 40),
+    ("TB", 1 << 40),
+    ("TiB", 1 << 40),
+    ("P", 1 << 50),
+    ("PiB", 1 << 50),
+];
+
+/// In seconds
+const DURATION_UNITS: [(&str, i64); 7] = [
+    ("s", 1),
+    ("m", 60),
+    ("min", 60),
+    ("h", 3600),
+    ("d", 86400),
+    ("w", 7 * 86400),
+    ("y", 365 * 86400),
+];
+
+/// A literal of a condition expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    // `10MiB`, in bytes
+    Size(u64),
+    // `7d`, in seconds
+    Duration(i64),
+    // `2024-01-15`, in seconds since the unix epoch
+    Date(i64),
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(s) => write!(f, "{}", s),
+            Literal::Int(n) | Literal::Duration(n) | Literal::Date(n) => write!(f, "{}", n),
+            Literal::Float(n) => write!(f, "{}", n),
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Size(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl Literal {
+    /// A number followed by a size or duration unit
+    fn with_unit(number: &str, unit: &str) -> Result<Self, String> {
+        let n: f64 = number
+            .parse()
+            .map_err(|_| format!("invalid number `{}`", number))?;
+
+        if let Some((_, bytes)) = SIZE_UNITS.iter().find(|(u, _)| *u == unit) {
+            return Ok(Literal::Size((n * *bytes as f64).round() as u64));
+        }
+
+        if let Some((_, seconds)) = DURATION_UNITS.iter().find(|(u, _)| *u == unit) {
+            return Ok(Literal::Duration((n * *seconds as f64).round() as i64));
+        }
+
+        Err(format!(
+            "unknown unit `{}`, expected a size (B, K, MiB, GB, ...) or a duration (s, m, h, d, w, y)",
+            unit
+        ))
+    }
+
+    /// `2024-01-15`, `2024-01-15T10:30` or `2024-01-15T10:30:00`,
+    /// in local time unless it ends with `Z`
+    fn date(text: &str) -> Result<Self, String> {
+        let (naive, utc) = match text.strip_suffix('Z') {
+            Some(naive) => (naive, true),
+            None => (text, false),
+        };
+
+        let datetime = NaiveDate::parse_from_str(naive, "%Y-%m-%d")
+            .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .or_else(|_| NaiveDateTime::parse_from_str(naive, "%Y-%m-%dT%H:%M:%S"))
+            .or_else(|_| NaiveDateTime::parse_from_str(naive, "%Y-%m-%dT%H:%M"))
+            .map_err(|_| format!("invalid date `{}`, expected YYYY-MM-DD[THH:MM[:SS]]", text))?;
+
+        let epoch = if utc {
+            Utc.from_utc_datetime(&datetime).timestamp()
+        } else {
+            Local
+                .from_local_datetime(&datetime)
+                .earliest()
+                .map(|d: DateTime<Local>| d.timestamp())
+                .ok_or_else(|| format!("`{}` doesn't exist in the local time zone", text))?
+        };
+
+        Ok(Literal::Date(epoch))
+    }
+
+    /// The size, duration or date in a string of the object form,
+    /// anything else stays a string
+    pub fn typed(text: &str) -> Self {
+        match tokenize(text).as_deref() {
+            Ok(
+                [
+                    (
+                        Token::Literal(
+                            literal @ (Literal::Size(_) | Literal::Duration(_) | Literal::Date(_)),
+                        ),
+                        _,
+                    ),
+                ],
+            ) => literal.clone(),
+            _ => Literal::String(text.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Variable(String),
+    Literal(Literal),
+}
+
+/// Right hand side of an operator
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rhs {
+    Operand(Operand),
+    // `['rs', 'toml']`, for `in` and `between`
+    List(Vec<Literal>),
+}
+
+/// A condition, parsed once when the config is loaded
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    // Patterns are compiled once, so they must be literals
+    Matches(Operand, Regex),
+    Glob(Operand, glob::Pattern),
+    In(Operand, Vec<Literal>),
+    // Inclusive
+    Between(Operand, Literal, Literal),
+    // A lone operand, true unless empty, `false` or zero
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    message: String,
+    // 1-based, in characters
+    column: usize,
+    source: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at column {} of \"{}\"",
+            self.message, self.column, self.source
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Literal),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::Literal(Literal::String(s)) => write!(f, "'{}'", s),
+            Token::Literal(l) => write!(f, "`{}`", l),
+            Token::Op(op) => write!(f, "`{}`", op),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Not => write!(f, "`!`"),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::LBracket => write!(f, "`[`"),
+            Token::RBracket => write!(f, "`]`"),
+            Token::Comma => write!(f, "`,`"),
+        }
+    }
+}
+
+/// Splits an expression into tokens and their columns
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let error = |message: String, at: usize| ParseError {
+        message,
+        column: at + 1,
+        source: source.to_string(),
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ',' => Token::Comma,
+            '&' if next == Some('&') => Token::And,
+            '|' if next == Some('|') => Token::Or,
+            '=' if next == Some('=') => Token::Op(Op::Equal),
+            '=' if next == Some('~') => Token::Op(Op::Matches),
+            '!' if next == Some('=') => Token::Op(Op::NotEqual),
+            '>' if next == Some('=') => Token::Op(Op::GreaterEqual),
+            '<' if next == Some('=') => Token::Op(Op::LessEqual),
+            '!' => Token::Not,
+            '>' => Token::Op(Op::Greater),
+            '<' => Token::Op(Op::Less),
+            '\'' | '"' => {
+                let mut s = String::new();
+                i += 1;
+
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error("unterminated string".to_string(), start)),
+                        Some(&q) if q == c => break,
+                        Some('\\') => {
+                            i += 1;
+
+                            match chars.get(i) {
+                                Some('n') => s.push('\n'),
+                                Some('t') => s.push('\t'),
+                                Some(&e) => s.push(e),
+                                None => {
+                                    return Err(error("unterminated string".to_string(), start));
+                                }
+                            }
+                        }
+                        Some(&ch) => s.push(ch),
+                    }
+
+                    i += 1;
+                }
+
+                Token::Literal(Literal::String(s))
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                i += 1;
+
+                while chars
+                    .get(i)
+                    .is_some_and(|ch| ch.is_ascii_digit() || *ch == '.' || *ch == '_')
+                {
+                    i += 1;
+                }
+
+                let text: String = chars[start..i].iter().filter(|&&ch| ch != '_').collect();
+
+                let literal = if text.len() == 4 && chars.get(i) == Some(&'-') {
+                    // A date, the time may follow
+                    while chars.get(i).is_some_and(|ch| {
+                        ch.is_ascii_digit() || matches!(ch, '-' | ':' | 'T' | 'Z')
+                    }) {
+                        i += 1;
+                    }
+
+                    let date: String = chars[start..i].iter().collect();
+                    Literal::date(&date).map_err(|e| error(e, start))?
+                } else if chars.get(i).is_some_and(|ch| ch.is_alphabetic()) {
+                    let unit_start = i;
+
+                    while chars.get(i).is_some_and(|ch| ch.is_alphabetic()) {
+                        i += 1;
+                    }
+
+                    let unit: String = chars[unit_start..i].iter().collect();
+                    Literal::with_unit(&text, &unit).map_err(|e| error(e, start))?
+                } else if let Ok(n) = text.parse::<i64>() {
+                    Literal::Int(n)
+                } else if let Ok(n) = text.parse::<f64>() {
+                    Literal::Float(n)
+                } else {
+                    return Err(error(format!("invalid number `{}`", text), start));
+                };
+
+                tokens.push((Token::Literal(literal), start + 1));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while chars
+                    .get(i)
+                    .is_some_and(|ch| ch.is_alphanumeric() || *ch == '_')
+                {
+                    i += 1;
+                }
+
+                let word: String = chars[start..i].iter().collect();
+
+                let token = match word.as_str() {
+                    "true" => Token::Literal(Literal::Bool(true)),
+                    "false" => Token::Literal(Literal::Bool(false)),
+                    "matches" => Token::Op(Op::Matches),
+                    "glob" => Token::Op(Op::Glob),
+                    "contains" => Token::Op(Op::Contains),
+                    "starts_with" => Token::Op(Op::StartsWith),
+                    "ends_with" => Token::Op(Op::EndsWith),
+                    "in" => Token::Op(Op::In),
+                    "between" => Token::Op(Op::Between),
+                    _ => Token::Ident(word),
+                };
+
+                tokens.push((token, start + 1));
+                continue;
+            }
+            '=' | '&' | '|' => {
+                return Err(error(
+                    format!("unexpected `{}`, did you mean `{}{}`?", c, c, c),
+                    start,
+                ));
+            }
+            c => return Err(error(format!("unexpected character `{}`", c), start)),
+        };
+
+        // Two character operators
+        i += match token {
+            Token::And | Token::Or => 2,
+            Token::Op(
+                Op::Equal | Op::NotEqual | Op::GreaterEqual | Op::LessEqual | Op::Matches,
+            ) => 2,
+            _ => 1,
+        };
+
+        tokens.push((token, start + 1));
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, from the lowest precedence:
+///
+/// ```text
+/// or      = and ("||" and)*
+/// and     = unary ("&&" unary)*
+/// unary   = "!" unary | primary
+/// primary = "(" or ")" | operand (op (operand | list))?
+/// list    = "[" (literal ("," literal)*)? "]"
+/// ```
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        let column = match self.tokens.get(self.pos) {
+            Some((_, column)) => *column,
+            None => self.source.chars().count() + 1,
+        };
+
+        ParseError {
+            message,
+            column,
+            source: self.source.to_string(),
+        }
+    }
+
+    /// What was found instead of the expected token
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(token) => format!("found {}", token),
+            None => String::from("found the end"),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            lhs = Expr::And(Box::new(lhs), Box::new(self.unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.or()?;
+
+            if self.peek() != Some(&Token::RParen) {
+                return Err(self.error(format!("expected `)`, {}", self.found())));
+            }
+
+            self.pos += 1;
+            return Ok(expr);
+        }
+
+        let lhs = self.operand()?;
+
+        let Some(Token::Op(op)) = self.peek().cloned() else {
+            return Ok(Expr::Truthy(lhs));
+        };
+
+        let op_pos = self.pos;
+        self.pos += 1;
+
+        let rhs = if self.peek() == Some(&Token::LBracket) {
+            Rhs::List(self.list()?)
+        } else {
+            Rhs::Operand(self.operand()?)
+        };
+
+        Expr::binary(lhs, op, rhs).map_err(|message| {
+            self.pos = op_pos;
+            self.error(message)
+        })
+    }
+
+    fn list(&mut self) -> Result<Vec<Literal>, ParseError> {
+        let mut list = Vec::new();
+        self.pos += 1;
+
+        loop {
+            match self.peek() {
+                Some(Token::RBracket) => break,
+                Some(Token::Literal(literal)) => {
+                    list.push(literal.clone());
+                    self.pos += 1;
+                }
+                _ => return Err(self.error(format!("expected a value or `]`, {}", self.found()))),
+            }
+
+            match self.peek() {
+                Some(Token::Comma) => self.pos += 1,
+                Some(Token::RBracket) => break,
+                _ => return Err(self.error(format!("expected `,` or `]`, {}", self.found()))),
+            }
+        }
+
+        self.pos += 1;
+        Ok(list)
+    }
+
+    fn operand(&mut self) -> Result<Operand, ParseError> {
+        let operand = match self.peek() {
+            Some(Token::Ident(name)) => Operand::Variable(name.clone()),
+            Some(Token::Literal(literal)) => Operand::Literal(literal.clone()),
+            _ => {
+                return Err(self.error(format!("expected a variable or a value, {}", self.found())));
+            }
+        };
+
+        self.pos += 1;
+        Ok(operand)
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+
+        let expr = parser.or()?;
+
+        if parser.pos < parser.tokens.len() {
+            return Err(parser.error(format!("expected `&&` or `||`, {}", parser.found())));
+        }
+
+        Ok(expr)
+    }
+
+    /// `lhs op rhs`, checking that the operator can take the right hand side
+    pub fn binary(lhs: Operand, op: Op, rhs: Rhs) -> Result<Self, String> {
+        let pattern = |rhs: Rhs| match rhs {
+            Rhs::Operand(Operand::Literal(Literal::String(s))) => Ok(s),
+            _ => Err(format!("`{}` needs a string pattern", op)),
+        };
+
+        match op {
+            Op::Matches => {
+                let pattern = pattern(rhs)?;
+                let regex = Regex::new(&pattern).map_err(|e| {
+                    // The syntax errors span several lines, the last one is the reason
+                    let e = e.to_string();
+                    let reason = e.lines().last().unwrap_or_default();
+                    format!("invalid regex: {}", reason.trim_start_matches("error: "))
+                })?;
+
+                Ok(Expr::Matches(lhs, regex))
+            }
+            Op::Glob => {
+                let pattern = pattern(rhs)?;
+                let glob = glob::Pattern::new(&pattern)
+                    .map_err(|e| format!("invalid glob `{}`: {}", pattern, e))?;
+
+                Ok(Expr::Glob(lhs, glob))
+            }
+            Op::In => match rhs {
+                Rhs::List(list) => Ok(Expr::In(lhs, list)),
+                Rhs::Operand(_) => Err(String::from("`in` needs a list, like `['a', 'b']`")),
+            },
+            Op::Between => {
+                let bounds = match rhs {
+                    Rhs::List(list) => <[Literal; 2]>::try_from(list).ok(),
+                    Rhs::Operand(_) => None,
+                };
+
+                match bounds {
+                    Some([lo, hi]) => Ok(Expr::Between(lhs, lo, hi)),
+                    None => Err(String::from(
+                        "`between` needs a list of two bounds, like `[1, 10]`",
+                    )),
+                }
+            }
+            _ => match rhs {
+                Rhs::Operand(rhs) => Ok(Expr::Compare(lhs, op, rhs)),
+                Rhs::List(_) => Err(format!("`{}` can't take a list", op)),
+            },
+        }
+    }
+
+    /// `variable == value`
+    pub fn equal(variable: &str, value: &str) -> Self {
+        Expr::Compare(
+            Operand::Variable(variable.to_string()),
+            Op::Equal,
+            Operand::Literal(Literal::String(value.to_string())),
+        )
+    }
+
+    /// Whether the expression reads the variable
+    pub fn uses(&self, variable: &str) -> bool {
+        let reads = |o: &Operand| matches!(o, Operand::Variable(v) if v == variable);
+
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => a.uses(variable) || b.uses(variable),
+            Expr::Not(e) => e.uses(variable),
+            Expr::Compare(a, _, b) => reads(a) || reads(b),
+            Expr::Matches(o, _)
+            | Expr::Glob(o, _)
+            | Expr::In(o, _)
+            | Expr::Between(o, ..)
+            | Expr::Truthy(o) => reads(o),
+        }
+    }
+
+    /// A comparison with an unknown variable is false
+    pub fn evaluate(&self, ctx: &HashMap<&'static str, Value>) -> bool {
+        match self {
+            Expr::And(a, b) => a.evaluate(ctx) && b.evaluate(ctx),
+            Expr::Or(a, b) => a.evaluate(ctx) || b.evaluate(ctx),
+            Expr::Not(e) => !e.evaluate(ctx),
+            Expr::Compare(a, op, b) => match (resolve(a, ctx), resolve(b, ctx)) {
+                (Some(a), Some(b)) => compare(&a, *op, &b),
+                _ => false,
+            },
+            Expr::Matches(o, regex) => resolve(o, ctx).is_some_and(|v| regex.is_match(&v.text)),
+            Expr::Glob(o, glob) => resolve(o, ctx).is_some_and(|v| glob.matches(&v.text)),
+            Expr::In(o, list) => resolve(o, ctx).is_some_and(|v| {
+                list.iter()
+                    .any(|l| compare(&v, Op::Equal, &Resolved::literal(l)))
+            }),
+            Expr::Between(o, lo, hi) => resolve(o, ctx).is_some_and(|v| {
+                compare(&v, Op::GreaterEqual, &Resolved::literal(lo))
+                    && compare(&v, Op::LessEqual, &Resolved::literal(hi))
+            }),
+            Expr::Truthy(o) => resolve(o, ctx).is_some_and(|v| {
+                !v.text.is_empty() && v.text != "false" && v.text.parse::<f64>() != Ok(0.0)
+            }),
+        }
+    }
+}
+
+/// How an operand compares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    // Number, size and date literals always compare as numbers
+    Number,
+    Duration,
+}
+
+/// An operand, once resolved against the context
+struct Resolved {
+    text: String,
+    kind: Kind,
+}
+
+impl Resolved {
+    fn literal(literal: &Literal) -> Self {
+        let kind = match literal {
+            Literal::String(_) | Literal::Bool(_) => Kind::Text,
+            Literal::Duration(_) => Kind::Duration,
+            _ => Kind::Number,
+        };
+
+        Self {
+            text: literal.to_string(),
+            kind,
+        }
+    }
+
+    /// The time since the epoch seconds of a variable, e.g. `{modified_epoch}`,
+    /// so it can be compared with a duration
+    fn age(&self) -> Self {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        match self.text.parse::<i64>() {
+            Ok(epoch) => Self {
+                text: (now - epoch).to_string(),
+                kind: Kind::Duration,
+            },
+            Err(_) => Self {
+                text: self.text.clone(),
+                kind: Kind::Text,
+            },
+        }
+    }
+}
+
+fn resolve(operand: &Operand, ctx: &HashMap<&'static str, Value>) -> Option<Resolved> {
+    match operand {
+        Operand::Variable(name) => ctx.get(name.as_str()).map(|v| Resolved {
+            text: v.to_string(),
+            kind: Kind::Text,
+        }),
+        Operand::Literal(literal) => Some(Resolved::literal(literal)),
+    }
+}
+
+/// Values are compared as numbers when both are,
+/// as strings unless a typed literal is involved.
+/// A duration compares with the age of the other side.
+fn compare(a: &Resolved, op: Op, b: &Resolved) -> bool {
+    use std::cmp::Ordering;
+
+    match op {
+        Op::Contains => return a.text.contains(&b.text),
+        Op::StartsWith => return a.text.starts_with(&b.text),
+        Op::EndsWith => return a.text.ends_with(&b.text),
+        _ => {}
+    }
+
+    let (aged_a, aged_b);
+
+    let (a, b) = match (a.kind, b.kind) {
+        (Kind::Duration, Kind::Text | Kind::Number) => {
+            aged_b = b.age();
+            (a, &aged_b)
+        }
+        (Kind::Text | Kind::Number, Kind::Duration) => {
+            aged_a = a.age();
+            (&aged_a, b)
+        }
+        _ => (a, b),
+    };
+
+    let ordering = if let (Ok(x), Ok(y)) = (a.text.parse::<i64>(), b.text.parse::<i64>()) {
+        Some(x.cmp(&y))
+    } else if let (Ok(x), Ok(y)) = (a.text.parse::<f64>(), b.text.parse::<f64>()) {
+        x.partial_cmp(&y)
+    } else {
+        None
+    };
+
+    let typed = a.kind != Kind::Text || b.kind != Kind::Text;
+
+    let ordering = match ordering {
+        Some(ordering) => ordering,
+        // No lexicographic fallback, `size > 1MiB` is false for an empty size
+        None if typed => return op == Op::NotEqual,
+        None => a.text.cmp(&b.text),
+    };
+
+    let equal = if typed {
+        ordering == Ordering::Equal
+    } else {
+        a.text == b.text
+    };
+
+    match op {
+        Op::Equal => equal,
+        Op::NotEqual => !equal,
+        Op::Greater => ordering == Ordering::Greater,
+        Op::Less => ordering == Ordering::Less,
+        Op::GreaterEqual => ordering != Ordering::Less,
+        Op::LessEqual => ordering != Ordering::Greater,
+        // Handled above, or they have their own expressions
+        Op::Contains
+        | Op::StartsWith
+        | Op::EndsWith
+        | Op::Matches
+        | Op::Glob
+        | Op::In
+        | Op::Between => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Condition;
+
+    fn ctx(vars: &[(&'static str, Value)]) -> HashMap<&'static str, Value> {
+        vars.iter().cloned().collect()
+    }
+
+    fn eval(source: &str, vars: &[(&'static str, Value)]) -> bool {
+        Expr::parse(source).unwrap().evaluate(&ctx(vars))
+    }
+
+    fn error(source: &str) -> ParseError {
+        Expr::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let vars = [
+            ("a", Value::Bool(true)),
+            ("b", Value::Bool(false)),
+            ("c", Value::Bool(false)),
+        ];
+
+        assert!(eval("a || b && c", &vars));
+        assert!(eval("b && c || a", &vars));
+        assert!(!eval("(a || b) && c", &vars));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        let vars = [("a", Value::Bool(true)), ("b", Value::Bool(false))];
+
+        assert!(!eval("!a && b", &vars));
+        assert!(eval("!(a && b)", &vars));
+        assert!(eval("!!a", &vars));
+        assert!(!eval("!(a || b)", &vars));
+    }
+
+    #[test]
+    fn comparisons_bind_tighter_than_logic() {
+        let vars = [("size", Value::Int(5)), ("name", Value::String("x".into()))];
+
+        assert!(eval("size > 3 && name == 'x'", &vars));
+        assert!(eval("size < 3 || !(name != 'x')", &vars));
+    }
+
+    #[test]
+    fn in_lists() {
+        let vars = [
+            ("extension", Value::String("rs".into())),
+            ("size", Value::Int(2)),
+        ];
+
+        assert!(eval("extension in ['rs', 'toml']", &vars));
+        assert!(!eval("extension in ['RS', 'toml']", &vars));
+        assert!(!eval("extension in []", &vars));
+        assert!(eval("size in [1, 2,]", &vars));
+        assert!(eval("size in [1KiB, 2B]", &vars));
+        assert!(!eval("missing in ['rs']", &vars));
+    }
+
+    #[test]
+    fn in_needs_a_list() {
+        let e = error("extension in 'rs'");
+        assert_eq!(e.column, 11);
+        assert_eq!(e.message, "`in` needs a list, like `['a', 'b']`");
+    }
+
+    #[test]
+    fn error_columns() {
+        let cases = [
+            ("x && && y", 6, "expected a variable or a value, found `&&`"),
+            ("(a || b", 8, "expected `)`, found the end"),
+            ("a b", 3, "expected `&&` or `||`, found `b`"),
+            ("ext in [1, x]", 12, "expected a value or `]`, found `x`"),
+            ("ext in [1 2]", 11, "expected `,` or `]`, found `2`"),
+            (
+                "size between [1]",
+                6,
+                "`between` needs a list of two bounds, like `[1, 10]`",
+            ),
+            ("size > [1]", 6, "`>` can't take a list"),
+            ("name glob size", 6, "`glob` needs a string pattern"),
+            ("a = b", 3, "unexpected `=`, did you mean `==`?"),
+            ("a & b", 3, "unexpected `&`, did you mean `&&`?"),
+            ("a # b", 3, "unexpected character `#`"),
+            ("name == 'abc", 9, "unterminated string"),
+            ("", 1, "expected a variable or a value, found the end"),
+        ];
+
+        for (source, column, message) in cases {
+            let e = error(source);
+            assert_eq!(
+                (e.column, e.message.as_str()),
+                (column, message),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn literal_error_columns() {
+        let e = error("size > 10XB");
+        assert_eq!(e.column, 8);
+        assert!(e.message.starts_with("unknown unit `XB`"));
+
+        let e = error("modified > 2024-13-01");
+        assert_eq!(e.column, 12);
+        assert!(e.message.starts_with("invalid date `2024-13-01`"));
+
+        let e = error("name matches '('");
+        assert_eq!(e.column, 6);
+        assert!(e.message.starts_with("invalid regex"));
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        assert_eq!(error("'é' == x y").column, 10);
+    }
+
+    #[test]
+    fn error_display() {
+        assert_eq!(
+            error("a && && b").to_string(),
+            "expected a variable or a value, found `&&` at column 6 of \"a && && b\""
+        );
+    }
+
+    fn legacy(json: &str) -> Result<Condition<String>, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn legacy_ordering_is_numeric() {
+        let bigger = legacy(r#"{ "variable": "size", "op": ">", "value": "10", "result": "x" }"#)
+            .unwrap()
+            .when;
+
+        // Not a lexicographic "9" > "10"
+        assert!(!bigger.evaluate(&ctx(&[("size", Value::Int(9))])));
+        assert!(bigger.evaluate(&ctx(&[("size", Value::Int(100))])));
+        assert!(!bigger.evaluate(&ctx(&[("size", Value::String(String::new()))])));
+    }
+
+    #[test]
+    fn legacy_ordering_takes_units() {
+        let bigger =
+            legacy(r#"{ "variable": "size", "op": "gt", "value": "1MiB", "result": "x" }"#)
+                .unwrap()
+                .when;
+
+        assert!(bigger.evaluate(&ctx(&[("size", Value::Int(2 << 20))])));
+        assert!(!bigger.evaluate(&ctx(&[("size", Value::Int(1 << 20))])));
+    }
+
+    #[test]
+    fn legacy_equality_is_textual() {
+        let equal =
+            legacy(r#"{ "variable": "name", "op": "==", "value": "2024-01-15", "result": "x" }"#)
+                .unwrap()
+                .when;
+
+        assert!(equal.evaluate(&ctx(&[("name", Value::String("2024-01-15".into()))])));
+    }
+
+    #[test]
+    fn legacy_lists() {
+        let within = legacy(
+            r#"{ "variable": "extension", "op": "in", "value": ["rs", "toml"], "result": "x" }"#,
+        )
+        .unwrap()
+        .when;
+
+        assert!(within.evaluate(&ctx(&[("extension", Value::String("toml".into()))])));
+        assert!(!within.evaluate(&ctx(&[("extension", Value::String("md".into()))])));
+
+        let between = legacy(
+            r#"{ "variable": "size", "op": "between", "value": ["1K", "2K"], "result": "x" }"#,
+        )
+        .unwrap()
+        .when;
+
+        assert!(between.evaluate(&ctx(&[("size", Value::Int(1500))])));
+        assert!(!between.evaluate(&ctx(&[("size", Value::Int(3000))])));
+    }
+
+    #[test]
+    fn legacy_errors() {
+        let both = r#"{ "when": "a", "variable": "a", "op": "==", "value": "b", "result": "x" }"#;
+        assert!(
+            legacy(both)
+                .unwrap_err()
+                .to_string()
+                .contains("either `when`")
+        );
+
+        let partial = r#"{ "variable": "a", "op": "==", "result": "x" }"#;
+        assert!(
+            legacy(partial)
+                .unwrap_err()
+                .to_string()
+                .contains("needs `when`")
+        );
+
+        let unlisted = r#"{ "variable": "a", "op": "in", "value": "b", "result": "x" }"#;
+        assert!(
+            legacy(unlisted)
+                .unwrap_err()
+                .to_string()
+                .contains("`in` needs a list")
+        );
+    }
+
+    #[test]
+    fn when_form() {
+        let condition = legacy(r#"{ "when": "size > 1K && !binary", "result": "x" }"#).unwrap();
+
+        let vars = [("size", Value::Int(2048)), ("binary", Value::Bool(false))];
+        assert!(condition.when.evaluate(&ctx(&vars)));
+        assert_eq!(condition.result, "x");
+    }
+}
@@ -0,0 +1,233 @@
+use git2::{Repository, Status, StatusOptions};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitStatus {
+    Conflicted,
+    Modified,
+    Staged,
+    Untracked,
+    Ignored,
+}
+
+impl Display for GitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status_str = match self {
+            GitStatus::Conflicted => "conflicted",
+            GitStatus::Modified => "modified",
+            GitStatus::Staged => "staged",
+            GitStatus::Untracked => "untracked",
+            GitStatus::Ignored => "ignored",
+        };
+
+        write!(f, "{}", status_str)
+    }
+}
+
+impl GitStatus {
+    /// Picks the most relevant status out of the libgit2 flags,
+    /// e.g. a staged file that was modified again is `modified`
+    fn from_flags(flags: Status) -> Option<Self> {
+        if flags.is_conflicted() {
+            Some(GitStatus::Conflicted)
+        } else if flags.intersects(
+            Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_TYPECHANGE | Status::WT_RENAMED,
+        ) {
+            Some(GitStatus::Modified)
+        } else if flags.intersects(
+            Status::INDEX_NEW
+                | Status::INDEX_MODIFIED
+                | Status::INDEX_DELETED
+                | Status::INDEX_RENAMED
+                | Status::INDEX_TYPECHANGE,
+        ) {
+            Some(GitStatus::Staged)
+        } else if flags.is_wt_new() {
+            Some(GitStatus::Untracked)
+        } else if flags.is_ignored() {
+            Some(GitStatus::Ignored)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct GitRepo {
+    repo: Repository,
+    // The listed directory, as given on the command line
+    root: PathBuf,
+    // The listed directory, relative to the working tree
+    prefix: PathBuf,
+    // Status of each changed path, relative to the working tree
+    files: HashMap<PathBuf, Status>,
+    // Union of the statuses of everything below each directory
+    dirs: HashMap<PathBuf, Status>,
+}
+
+impl GitRepo {
+    /// Opens the repository containing `root`, if any
+    pub fn open<P: AsRef<Path>>(root: P) -> Option<Self> {
+        let root = root.as_ref();
+        let repo = Repository::discover(root).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let prefix = root
+            .canonicalize()
+            .ok()?
+            .strip_prefix(&workdir)
+            .ok()?
+            .to_path_buf();
+
+        Some(Self {
+            repo,
+            root: root.to_path_buf(),
+            prefix,
+            files: HashMap::new(),
+            dirs: HashMap::new(),
+        })
+    }
+
+    /// Reads the index and the working tree.
+    /// Must be called before `status`, it's the expensive part.
+    pub fn load_statuses(&mut self) {
+        let mut options = StatusOptions::new();
+
+        options
+            .include_untracked(true)
+            .include_ignored(true)
+            .recurse_untracked_dirs(false)
+            .recurse_ignored_dirs(false)
+            .exclude_submodules(true);
+
+        // Only the listed directory, not the whole working tree
+        if !self.prefix.as_os_str().is_empty() {
+            options.pathspec(&self.prefix).disable_pathspec_match(true);
+        }
+
+        let Ok(statuses) = self.repo.statuses(Some(&mut options)) else {
+            return;
+        };
+
+        for entry in statuses.iter() {
+            let Ok(path) = entry.path() else {
+                continue;
+            };
+
+            let path = PathBuf::from(path);
+            let flags = entry.status();
+
+            // Ignored files don't make their parents ignored
+            if !flags.is_ignored() {
+                for ancestor in path.ancestors().skip(1) {
+                    *self
+                        .dirs
+                        .entry(ancestor.to_path_buf())
+                        .or_insert(Status::empty()) |= flags;
+                }
+            }
+
+            self.files.insert(path, flags);
+        }
+
+        // Inside an untracked or ignored directory, only that directory is reported,
+        // and the pathspec leaves it out
+        if let Some(flags) = self.enclosing_status() {
+            self.files.insert(self.prefix.clone(), flags);
+        }
+    }
+
+    /// Status shared by the whole listed directory, if it is ignored or has nothing tracked
+    fn enclosing_status(&self) -> Option<Status> {
+        if self.prefix.as_os_str().is_empty() {
+            return None;
+        }
+
+        if self
+            .repo
+            .status_should_ignore(&self.prefix)
+            .unwrap_or(false)
+        {
+            return Some(Status::IGNORED);
+        }
+
+        let index = self.repo.index().ok()?;
+        let prefix = format!("{}/", self.prefix.to_string_lossy());
+
+        index.find_prefix(prefix).is_err().then_some(Status::WT_NEW)
+    }
+
+    /// Status of an entry yielded while walking the root
+    pub fn status<P: AsRef<Path>>(&self, path: P) -> Option<GitStatus> {
+        let relative = path.as_ref().strip_prefix(&self.root).ok()?;
+        let key = self.prefix.join(relative);
+
+        if let Some(flags) = self.files.get(&key) {
+            return GitStatus::from_flags(*flags);
+        }
+
+        if let Some(flags) = self.dirs.get(&key) {
+            return GitStatus::from_flags(*flags);
+        }
+
+        // Untracked and ignored directories are reported as a whole,
+        // so their content inherits their status
+        key.ancestors()
+            .skip(1)
+            .filter_map(|a| self.files.get(a))
+            .find(|flags| flags.is_wt_new() || flags.is_ignored())
+            .and_then(|flags| GitStatus::from_flags(*flags))
+    }
+
+    /// Name of the current branch, or the short hash of a detached head
+    pub fn branch(&self) -> Option<String> {
+        let head = self.repo.head().ok()?;
+
+        if head.is_branch() {
+            return head.shorthand().ok().map(|s| s.to_string());
+        }
+
+        head.target()
+            .map(|oid| oid.to_string().chars().take(7).collect())
+    }
+
+    /// Branch and upstream state, like `git status -sb`
+    /// (e.g. `main...origin/main [ahead 1, behind 2]`)
+    pub fn header(&self) -> Option<String> {
+        let branch = self.branch()?;
+        let mut header = branch.clone();
+
+        let Ok(local) = self.repo.find_branch(&branch, git2::BranchType::Local) else {
+            return Some(header);
+        };
+
+        let Ok(upstream) = local.upstream() else {
+            return Some(header);
+        };
+
+        if let Ok(Some(name)) = upstream.name() {
+            header.push_str(&format!("...{}", name));
+        }
+
+        if let (Some(l), Some(u)) = (local.get().target(), upstream.get().target())
+            && let Ok((ahead, behind)) = self.repo.graph_ahead_behind(l, u)
+        {
+            let state = match (ahead, behind) {
+                (0, 0) => None,
+                (a, 0) => Some(format!("ahead {}", a)),
+                (0, b) => Some(format!("behind {}", b)),
+                (a, b) => Some(format!("ahead {}, behind {}", a, b)),
+            };
+
+            if let Some(state) = state {
+                header.push_str(&format!(" [{}]", state));
+            }
+        }
+
+        Some(header)
+    }
+}
@@ -0,0 +1,393 @@
+use crate::config::{FileKind, If};
+use figura::Value;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Built-in icons, looked up by file name, then extension, then kind
+struct Preset {
+    names: &'static [(&'static str, &'static str)],
+    // Lowercase, compound ones like `tar.gz` included
+    extensions: &'static [(&'static str, &'static str)],
+    kinds: &'static [(FileKind, &'static str)],
+    // For entries matched by nothing
+    fallback: &'static str,
+}
+
+/// The type letters of `ls -l`
+const ASCII: Preset = Preset {
+    names: &[],
+    extensions: &[],
+    kinds: &[
+        (FileKind::Directory, "d"),
+        (FileKind::StickyDirectory, "d"),
+        (FileKind::OtherWritable, "d"),
+        (FileKind::SymlinkFile, "l"),
+        (FileKind::SymlinkDirectory, "l"),
+        (FileKind::BrokenSymlink, "l"),
+        (FileKind::Fifo, "p"),
+        (FileKind::Socket, "s"),
+        (FileKind::BlockDevice, "b"),
+        (FileKind::CharDevice, "c"),
+    ],
+    fallback: "f",
+};
+
+/// Nerd Fonts v3 glyphs
+const NERD_FONT: Preset = Preset {
+    names: &[
+        ("Cargo.toml", "\u{e7a8}"),
+        ("Cargo.lock", "\u{e7a8}"),
+        ("Dockerfile", "\u{f308}"),
+        ("docker-compose.yml", "\u{f308}"),
+        ("compose.yaml", "\u{f308}"),
+        (".dockerignore", "\u{f308}"),
+        ("Makefile", "\u{f423}"),
+        ("CMakeLists.txt", "\u{f423}"),
+        ("justfile", "\u{f423}"),
+        (".git", "\u{e5fb}"),
+        (".github", "\u{e5fd}"),
+        (".gitignore", "\u{f1d3}"),
+        (".gitattributes", "\u{f1d3}"),
+        (".gitmodules", "\u{f1d3}"),
+        ("LICENSE", "\u{e60a}"),
+        ("README", "\u{f05a}"),
+        ("README.md", "\u{f05a}"),
+        ("package.json", "\u{e71e}"),
+        ("package-lock.json", "\u{e71e}"),
+        ("node_modules", "\u{e5fa}"),
+        ("go.mod", "\u{e627}"),
+        ("go.sum", "\u{e627}"),
+        ("flake.nix", "\u{f313}"),
+        (".editorconfig", "\u{e615}"),
+        (".bashrc", "\u{f489}"),
+        (".zshrc", "\u{f489}"),
+        (".profile", "\u{f489}"),
+    ],
+    extensions: &[
+        ("rs", "\u{e7a8}"),
+        ("py", "\u{e606}"),
+        ("js", "\u{e74e}"),
+        ("mjs", "\u{e74e}"),
+        ("ts", "\u{e628}"),
+        ("jsx", "\u{e7ba}"),
+        ("tsx", "\u{e7ba}"),
+        ("go", "\u{e627}"),
+        ("c", "\u{e61e}"),
+        ("h", "\u{f0fd}"),
+        ("cpp", "\u{e61d}"),
+        ("cc", "\u{e61d}"),
+        ("hpp", "\u{e61d}"),
+        ("java", "\u{e738}"),
+        ("kt", "\u{e634}"),
+        ("rb", "\u{e791}"),
+        ("php", "\u{e73d}"),
+        ("lua", "\u{e620}"),
+        ("swift", "\u{e755}"),
+        ("hs", "\u{e777}"),
+        ("ex", "\u{e62d}"),
+        ("exs", "\u{e62d}"),
+        ("zig", "\u{e6a9}"),
+        ("dart", "\u{e798}"),
+        ("nix", "\u{f313}"),
+        ("vim", "\u{e62b}"),
+        ("sh", "\u{f489}"),
+        ("bash", "\u{f489}"),
+        ("zsh", "\u{f489}"),
+        ("fish", "\u{f489}"),
+        ("html", "\u{e736}"),
+        ("css", "\u{e749}"),
+        ("scss", "\u{e603}"),
+        ("json", "\u{e60b}"),
+        ("toml", "\u{e615}"),
+        ("yaml", "\u{e615}"),
+        ("yml", "\u{e615}"),
+        ("ini", "\u{e615}"),
+        ("conf", "\u{e615}"),
+        ("md", "\u{f48a}"),
+        ("txt", "\u{f15c}"),
+        ("lock", "\u{f023}"),
+        ("diff", "\u{f440}"),
+        ("patch", "\u{f440}"),
+        ("sql", "\u{f1c0}"),
+        ("db", "\u{f1c0}"),
+        ("sqlite", "\u{f1c0}"),
+        ("png", "\u{f1c5}"),
+        ("jpg", "\u{f1c5}"),
+        ("jpeg", "\u{f1c5}"),
+        ("gif", "\u{f1c5}"),
+        ("webp", "\u{f1c5}"),
+        ("svg", "\u{f1c5}"),
+        ("ico", "\u{f1c5}"),
+        ("bmp", "\u{f1c5}"),
+        ("mp3", "\u{f1c7}"),
+        ("flac", "\u{f1c7}"),
+        ("wav", "\u{f1c7}"),
+        ("ogg", "\u{f1c7}"),
+        ("mp4", "\u{f1c8}"),
+        ("mkv", "\u{f1c8}"),
+        ("mov", "\u{f1c8}"),
+        ("avi", "\u{f1c8}"),
+        ("webm", "\u{f1c8}"),
+        ("zip", "\u{f410}"),
+        ("tar", "\u{f410}"),
+        ("gz", "\u{f410}"),
+        ("tgz", "\u{f410}"),
+        ("xz", "\u{f410}"),
+        ("bz2", "\u{f410}"),
+        ("zst", "\u{f410}"),
+        ("7z", "\u{f410}"),
+        ("rar", "\u{f410}"),
+        ("pdf", "\u{f1c1}"),
+        ("doc", "\u{f1c2}"),
+        ("docx", "\u{f1c2}"),
+        ("xls", "\u{f1c3}"),
+        ("xlsx", "\u{f1c3}"),
+        ("csv", "\u{f1c3}"),
+        ("ppt", "\u{f1c4}"),
+        ("pptx", "\u{f1c4}"),
+    ],
+    kinds: &[
+        (FileKind::Directory, "\u{f07b}"),
+        (FileKind::StickyDirectory, "\u{f07b}"),
+        (FileKind::OtherWritable, "\u{f07b}"),
+        (FileKind::SymlinkFile, "\u{f481}"),
+        (FileKind::SymlinkDirectory, "\u{f482}"),
+        (FileKind::BrokenSymlink, "\u{f127}"),
+        (FileKind::Executable, "\u{f489}"),
+        (FileKind::Fifo, "\u{f0ec}"),
+        (FileKind::Socket, "\u{f1e6}"),
+        (FileKind::BlockDevice, "\u{f0a0}"),
+        (FileKind::CharDevice, "\u{f11c}"),
+        (FileKind::Setuid, "\u{f023}"),
+        (FileKind::Setgid, "\u{f023}"),
+    ],
+    fallback: "\u{f15b}",
+};
+
+/// Only emoji that are wide without a variation selector,
+/// so that columns stay aligned
+const EMOJI: Preset = Preset {
+    names: &[
+        ("Cargo.toml", "🦀"),
+        ("Cargo.lock", "🦀"),
+        ("Dockerfile", "🐳"),
+        ("docker-compose.yml", "🐳"),
+        ("compose.yaml", "🐳"),
+        ("Makefile", "🔨"),
+        ("CMakeLists.txt", "🔨"),
+        ("justfile", "🔨"),
+        (".git", "🌱"),
+        (".gitignore", "🌱"),
+        (".gitattributes", "🌱"),
+        (".gitmodules", "🌱"),
+        ("LICENSE", "📜"),
+        ("README", "📖"),
+        ("README.md", "📖"),
+        ("package.json", "📦"),
+        ("node_modules", "📦"),
+    ],
+    extensions: &[
+        ("rs", "🦀"),
+        ("py", "🐍"),
+        ("go", "🐹"),
+        ("rb", "💎"),
+        ("java", "☕"),
+        ("js", "📜"),
+        ("ts", "📜"),
+        ("sh", "🐚"),
+        ("bash", "🐚"),
+        ("zsh", "🐚"),
+        ("fish", "🐚"),
+        ("html", "🌐"),
+        ("css", "🎨"),
+        ("md", "📝"),
+        ("txt", "📝"),
+        ("json", "📋"),
+        ("toml", "📋"),
+        ("yaml", "📋"),
+        ("yml", "📋"),
+        ("lock", "🔒"),
+        ("sql", "💾"),
+        ("db", "💾"),
+        ("sqlite", "💾"),
+        ("png", "🎨"),
+        ("jpg", "🎨"),
+        ("jpeg", "🎨"),
+        ("gif", "🎨"),
+        ("webp", "🎨"),
+        ("svg", "🎨"),
+        ("mp3", "🎵"),
+        ("flac", "🎵"),
+        ("wav", "🎵"),
+        ("ogg", "🎵"),
+        ("mp4", "🎬"),
+        ("mkv", "🎬"),
+        ("mov", "🎬"),
+        ("webm", "🎬"),
+        ("zip", "📦"),
+        ("tar", "📦"),
+        ("gz", "📦"),
+        ("tgz", "📦"),
+        ("xz", "📦"),
+        ("zst", "📦"),
+        ("7z", "📦"),
+        ("pdf", "📕"),
+        ("doc", "📘"),
+        ("docx", "📘"),
+        ("xls", "📊"),
+        ("xlsx", "📊"),
+        ("csv", "📊"),
+    ],
+    kinds: &[
+        (FileKind::Directory, "📁"),
+        (FileKind::StickyDirectory, "📁"),
+        (FileKind::OtherWritable, "📂"),
+        (FileKind::SymlinkFile, "🔗"),
+        (FileKind::SymlinkDirectory, "🔗"),
+        (FileKind::BrokenSymlink, "💔"),
+        (FileKind::Executable, "🔧"),
+        (FileKind::Fifo, "🚰"),
+        (FileKind::Socket, "🔌"),
+        (FileKind::BlockDevice, "💽"),
+        (FileKind::CharDevice, "📟"),
+        (FileKind::Setuid, "🔒"),
+        (FileKind::Setgid, "🔒"),
+    ],
+    fallback: "📄",
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IconPreset {
+    #[default]
+    Ascii,
+    NerdFont,
+    Emoji,
+}
+
+impl<'de> Deserialize<'de> for IconPreset {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.to_lowercase().as_str() {
+            "ascii" => Ok(IconPreset::Ascii),
+            "nerd_font" | "nerdfont" => Ok(IconPreset::NerdFont),
+            "emoji" => Ok(IconPreset::Emoji),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid icon preset: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl IconPreset {
+    fn table(&self) -> &'static Preset {
+        match self {
+            IconPreset::Ascii => &ASCII,
+            IconPreset::NerdFont => &NERD_FONT,
+            IconPreset::Emoji => &EMOJI,
+        }
+    }
+}
+
+/// Icon maps of the config, checked before the ones of the preset
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Icons {
+    pub preset: IconPreset,
+    // Exact file names, e.g. `Makefile`
+    pub names: HashMap<String, String>,
+    // Without the dot, e.g. `rs` or `tar.gz`, case-insensitive
+    #[serde(deserialize_with = "lowercase_keys")]
+    pub extensions: HashMap<String, String>,
+    pub kinds: HashMap<FileKind, String>,
+}
+
+impl Icons {
+    /// The icon of an entry: the conditions of `icon` first, then the icon maps,
+    /// then the default of `icon`, then the fallback of the preset
+    pub fn resolve(
+        &self,
+        icon: &If<String>,
+        name: &str,
+        kind: FileKind,
+        ctx: &HashMap<&'static str, Value>,
+    ) -> String {
+        if let Some(result) = icon.matching(ctx) {
+            return result.clone();
+        }
+
+        if let Some(found) = self.lookup(name, kind) {
+            return found.to_string();
+        }
+
+        match icon.default_result() {
+            Some(default) => default.clone(),
+            None => self.preset.table().fallback.to_string(),
+        }
+    }
+
+    fn lookup(&self, name: &str, kind: FileKind) -> Option<&str> {
+        let lower = name.to_lowercase();
+
+        // Longest first, so `tar.gz` wins over `gz`.
+        // A leading dot is part of the name (`.bashrc` has no extension).
+        // Directories named like `foo.d` are still directories
+        let suffixes: Vec<&str> = if !kind.is_dir() && kind != FileKind::SymlinkDirectory {
+            lower
+                .char_indices()
+                .skip(1)
+                .filter(|(_, c)| *c == '.')
+                .map(|(i, _)| &lower[i + 1..])
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // Any icon of the config wins over the preset, even a less specific one
+        let user = self
+            .names
+            .get(name)
+            .or_else(|| suffixes.iter().find_map(|ext| self.extensions.get(*ext)))
+            .or_else(|| self.kinds.get(&kind))
+            .map(String::as_str);
+
+        user.or_else(|| {
+            let preset = self.preset.table();
+
+            find(preset.names, |n| n == name)
+                .or_else(|| {
+                    suffixes
+                        .iter()
+                        .find_map(|ext| find(preset.extensions, |e| e == *ext))
+                })
+                .or_else(|| find(preset.kinds, |k| k == kind))
+        })
+    }
+}
+
+/// The icon of the first matching key of a preset table
+fn find<K: Copy>(
+    table: &'static [(K, &'static str)],
+    matches: impl Fn(K) -> bool,
+) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(key, _)| matches(*key))
+        .map(|(_, icon)| *icon)
+}
+
+/// Extensions are matched against the lowercased file name
+fn lowercase_keys<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let map: HashMap<String, String> = Deserialize::deserialize(deserializer)?;
+
+    Ok(map
+        .into_iter()
+        .map(|(key, icon)| (key.to_lowercase(), icon))
+        .collect())
+}
@@ -0,0 +1,158 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Ignore files read in each directory, in order of increasing precedence.
+/// `.gitignore` comes first but only applies inside a git repository
+const IGNORE_FILES: [&str; 2] = [".ignore", ".plsignore"];
+const GITIGNORE: &str = ".gitignore";
+
+#[derive(Debug)]
+struct Roots {
+    // The walked directory, as given to the walker
+    walk: PathBuf,
+    // The walked directory as an absolute path,
+    // which is what the matchers are rooted at
+    absolute: PathBuf,
+}
+
+/// The ignore rules that apply to the content of a directory:
+/// the ones of its own ignore files and of all its ancestors
+#[derive(Debug, Clone)]
+pub struct Ignores {
+    // From the outermost directory to the innermost
+    matchers: Vec<Arc<Gitignore>>,
+    // The user's global excludes file (`core.excludesFile`)
+    global: Arc<Gitignore>,
+    // Git's own rules (`.gitignore`, excludes files, `.git`) only apply in a repository
+    in_repo: bool,
+    roots: Arc<Roots>,
+}
+
+impl Ignores {
+    /// Loads the rules that apply to the content of `root`,
+    /// including the ignore files of its ancestors up to the repository root
+    pub fn new<P: AsRef<Path>>(root: P) -> Arc<Self> {
+        let walk = root.as_ref().to_path_buf();
+        let absolute = std::path::absolute(&walk).unwrap_or_else(|_| walk.clone());
+
+        // Rules of parent directories only apply inside the same repository
+        let repo_root = absolute.ancestors().position(|a| a.join(".git").exists());
+
+        let ancestors: Vec<&Path> = match repo_root {
+            Some(i) => absolute.ancestors().take(i + 1).collect(),
+            None => vec![absolute.as_path()],
+        };
+
+        let in_repo = repo_root.is_some();
+
+        let matchers = ancestors
+            .iter()
+            .rev()
+            .filter_map(|dir| Self::load(dir, in_repo))
+            .collect();
+
+        let global = if in_repo {
+            Gitignore::global().0
+        } else {
+            Gitignore::empty()
+        };
+
+        Arc::new(Self {
+            matchers,
+            global: Arc::new(global),
+            in_repo,
+            roots: Arc::new(Roots { walk, absolute }),
+        })
+    }
+
+    /// Builds a matcher out of the ignore files in `dir` (the git ones if `git` is set),
+    /// or `None` if there are none
+    fn load(dir: &Path, git: bool) -> Option<Arc<Gitignore>> {
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+
+        let git_files = if git {
+            vec![
+                dir.join(".git").join("info").join("exclude"),
+                dir.join(GITIGNORE),
+            ]
+        } else {
+            Vec::new()
+        };
+
+        for file in git_files
+            .into_iter()
+            .chain(IGNORE_FILES.iter().map(|f| dir.join(f)))
+        {
+            if file.is_file() {
+                // Invalid globs are skipped, the rest of the file still applies
+                _ = builder.add(file);
+                found = true;
+            }
+        }
+
+        if !found {
+            return None;
+        }
+
+        builder.build().ok().map(Arc::new)
+    }
+
+    /// The rules that apply to the content of `dir`, a subdirectory
+    pub fn child<P: AsRef<Path>>(self: &Arc<Self>, dir: P) -> Arc<Self> {
+        let absolute = self.absolute(dir.as_ref());
+
+        // A repository nested in a plain directory starts applying git's rules
+        let enters_repo = !self.in_repo && absolute.join(".git").exists();
+
+        let matcher = Self::load(&absolute, self.in_repo || enters_repo);
+
+        if matcher.is_none() && !enters_repo {
+            return Arc::clone(self);
+        }
+
+        let mut child = Self::clone(self);
+        child.matchers.extend(matcher);
+
+        if enters_repo {
+            child.in_repo = true;
+            child.global = Arc::new(Gitignore::global().0);
+        }
+
+        Arc::new(child)
+    }
+
+    fn absolute(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.roots.walk) {
+            Ok(relative) => self.roots.absolute.join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    pub fn is_ignored<P: AsRef<Path>>(&self, path: P, is_dir: bool) -> bool {
+        let absolute = self.absolute(path.as_ref());
+
+        // Git's own directory, even when hidden files are shown
+        if self.in_repo && absolute.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+
+        // The innermost ignore file that has an opinion wins
+        for matcher in self.matchers.iter().rev() {
+            let m = matcher.matched(&absolute, is_dir);
+
+            if m.is_ignore() {
+                return true;
+            }
+
+            if m.is_whitelist() {
+                return false;
+            }
+        }
+
+        self.global.matched(&absolute, is_dir).is_ignore()
+    }
+}
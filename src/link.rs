@@ -0,0 +1,98 @@
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Same limit as the kernel (`MAXSYMLINKS`)
+const MAX_HOPS: usize = 40;
+
+/// Why a symlink can't be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkError {
+    Missing,
+    Loop,
+    PermissionDenied,
+    Other,
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let error_str = match self {
+            LinkError::Missing => "missing",
+            LinkError::Loop => "loop",
+            LinkError::PermissionDenied => "permission_denied",
+            LinkError::Other => "other",
+        };
+
+        write!(f, "{}", error_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    // The raw `readlink` value
+    pub target: String,
+    // Every path the link goes through, the first hop included
+    pub chain: Vec<PathBuf>,
+    // The canonical path of the final target
+    pub resolved: Option<PathBuf>,
+    pub error: Option<LinkError>,
+}
+
+impl Link {
+    /// Reads the symlink at `path`, `None` if it isn't one
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+        let target = std::fs::read_link(path).ok()?;
+
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = path.to_path_buf();
+        let mut next = target.clone();
+        let mut looped = false;
+
+        loop {
+            // Relative targets are relative to the directory of the link
+            current = match current.parent() {
+                Some(parent) => parent.join(&next),
+                None => next.clone(),
+            };
+
+            if !visited.insert(current.clone()) || chain.len() >= MAX_HOPS {
+                looped = true;
+                break;
+            }
+
+            chain.push(current.clone());
+
+            match std::fs::read_link(&current) {
+                Ok(t) => next = t,
+                Err(_) => break,
+            }
+        }
+
+        let (resolved, error) = match std::fs::canonicalize(path) {
+            Ok(resolved) => (Some(resolved), None),
+            Err(_) if looped => (None, Some(LinkError::Loop)),
+            Err(e) => (
+                None,
+                Some(match e.kind() {
+                    ErrorKind::NotFound => LinkError::Missing,
+                    ErrorKind::PermissionDenied => LinkError::PermissionDenied,
+                    _ => LinkError::Other,
+                }),
+            ),
+        };
+
+        Some(Self {
+            target: target.to_string_lossy().to_string(),
+            chain,
+            resolved,
+            error,
+        })
+    }
+}
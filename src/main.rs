@@ -1,12 +1,33 @@
+mod acl;
+mod attrs;
+mod color;
 mod commands;
 mod config;
+mod content;
+mod dircolors;
+mod dirsize;
+mod expr;
+mod git;
+mod icons;
+mod ignores;
+mod link;
+mod mime;
+mod output;
+mod sort;
 mod table;
+mod theme;
+mod tree;
 mod util;
 mod walk;
+mod xattrs;
 
-use crate::config::Config;
+use crate::{
+    color::ColorMode,
+    config::{Config, Layout, SortKey},
+    output::OutputFormat,
+};
 use clap::{Parser, Subcommand};
-use std::{path::PathBuf, usize};
+use std::{path::PathBuf, process::ExitCode};
 
 #[derive(Debug, Clone, Parser)]
 pub struct FindArgs {
@@ -30,6 +51,10 @@ pub struct FindArgs {
 
     #[arg(short, long, default_value_t = false)]
     timed: bool,
+
+    /// Don't skip entries matched by .gitignore, .ignore and .plsignore files
+    #[arg(long, default_value_t = false)]
+    no_ignore: bool,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -42,6 +67,10 @@ pub struct SizeArgs {
 
     #[arg(short, long, default_value_t = usize::MAX)]
     depth: usize,
+
+    /// Don't skip entries matched by .gitignore, .ignore and .plsignore files
+    #[arg(long, default_value_t = false)]
+    no_ignore: bool,
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -52,8 +81,9 @@ enum Command {
 
 #[derive(Debug, Clone, Parser)]
 struct Args {
+    /// Files, directories or glob patterns to list
     #[arg(index = 1, default_value = ".")]
-    path: PathBuf,
+    paths: Vec<PathBuf>,
 
     #[arg(short, long, default_value_t = false)]
     all: bool,
@@ -67,23 +97,85 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     pad_names: bool,
 
+    /// Sort keys, in order of priority (e.g. `size,name`)
+    #[arg(short, long, value_delimiter = ',')]
+    sort: Option<Vec<SortKey>>,
+
+    #[arg(short, long, default_value_t = false)]
+    reverse: bool,
+
+    /// Draw recursive listings as a tree
+    #[arg(short, long, default_value_t = false)]
+    tree: bool,
+
+    /// How entries are laid out (table, grid, across)
+    #[arg(long)]
+    layout: Option<Layout>,
+
+    /// Pack names in columns, filled top to bottom (same as `--layout grid`)
+    #[arg(short = 'C', default_value_t = false)]
+    grid: bool,
+
+    /// Pack names in columns, filled left to right (same as `--layout across`)
+    #[arg(short = 'x', default_value_t = false)]
+    across: bool,
+
+    /// Don't skip entries matched by .gitignore, .ignore and .plsignore files
+    #[arg(long, default_value_t = false)]
+    no_ignore: bool,
+
+    /// List ignored entries too, they can be styled with the `ignored` variable
+    #[arg(long, default_value_t = false)]
+    show_ignored: bool,
+
+    /// When to color the output (auto, always, never), `--color` alone is always
+    #[arg(
+        long,
+        global = true,
+        default_value = "auto",
+        num_args = 0..=1,
+        default_missing_value = "always",
+        require_equals = true
+    )]
+    color: ColorMode,
+
+    /// Fill every field of the structured output, even the expensive ones
+    /// (git status, xattrs, ACLs, attributes, content types and text content)
+    #[arg(long, default_value_t = false)]
+    all_fields: bool,
+
+    /// Theme to style entries with, instead of the configured one
+    #[arg(long, global = true)]
+    theme: Option<String>,
+
+    /// Print raw data instead of styled text (text, json, ndjson, csv, tsv)
+    #[arg(short, long, global = true, default_value = "text")]
+    output: OutputFormat,
+
     #[command(subcommand)]
     subcommand: Option<Command>,
 }
 
-fn main() {
+fn main() -> ExitCode {
+    // Exit quietly when the reader goes away (`pls | head`)
+    // instead of panicking on the next print
+    // SAFETY: called before any other thread is spawned
+    unsafe { libc::signal(libc::SIGPIPE, libc::SIG_DFL) };
+
     let args = Args::parse();
-    let config = match Config::parse() {
+    args.color.init();
+
+    let config = match Config::parse(args.theme.as_deref()) {
         Ok(c) => c,
         Err(e) => {
             eprintln!("Error parsing config: {}", e);
-            return;
+            return ExitCode::FAILURE;
         }
     };
 
-    match args.subcommand {
-        Some(Command::Find(args)) => commands::find::execute(&args),
-        Some(Command::Size(args)) => commands::size::execute(&args),
-        _ => commands::list::execute(&args, &config.ls),
+    match &args.subcommand {
+        Some(Command::Find(find_args)) => commands::find::execute(find_args, args.output),
+        Some(Command::Size(size_args)) => commands::size::execute(size_args, args.output),
+        _ => commands::list::execute(&args, &config.ls, args.output),
     }
 }
@@ -0,0 +1,378 @@
+use serde::Serialize;
+use std::{fmt::Display, fs::File, io::Read, path::Path};
+
+/// Enough for every signature, `ustar` is at 257
+const HEADER_LEN: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Text,
+    Image,
+    Archive,
+    Audio,
+    Video,
+    Binary,
+    Document,
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let category_str = match self {
+            Category::Text => "text",
+            Category::Image => "image",
+            Category::Archive => "archive",
+            Category::Audio => "audio",
+            Category::Video => "video",
+            Category::Binary => "binary",
+            Category::Document => "document",
+        };
+
+        write!(f, "{}", category_str)
+    }
+}
+
+/// Bytes expected at an offset of the header
+struct Signature {
+    offset: usize,
+    magic: &'static [u8],
+    mime: &'static str,
+    category: Category,
+    // Validates the rest of the header, for magics short enough to start a text
+    check: Option<fn(&[u8]) -> bool>,
+}
+
+const fn sig(
+    offset: usize,
+    magic: &'static [u8],
+    mime: &'static str,
+    category: Category,
+) -> Signature {
+    Signature {
+        offset,
+        magic,
+        mime,
+        category,
+        check: None,
+    }
+}
+
+const fn checked(
+    magic: &'static [u8],
+    mime: &'static str,
+    category: Category,
+    check: fn(&[u8]) -> bool,
+) -> Signature {
+    Signature {
+        check: Some(check),
+        ..sig(0, magic, mime, category)
+    }
+}
+
+fn u32_le(header: &[u8], offset: usize) -> Option<u32> {
+    let bytes = header.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// The size of the DIB header that follows the 14 bytes of the file header
+fn is_bmp(header: &[u8]) -> bool {
+    u32_le(header, 14).is_some_and(|size| matches!(size, 12 | 40 | 52 | 56 | 64 | 108 | 124))
+}
+
+/// ID3v2.2 to 2.4, the revision is never 0xff
+fn is_id3(header: &[u8]) -> bool {
+    matches!(header.get(3), Some(2..=4)) && header.get(4).is_some_and(|&r| r != 0xff)
+}
+
+/// Version 1 is the only one
+fn is_flv(header: &[u8]) -> bool {
+    header.get(3) == Some(&1)
+}
+
+/// Block size digit, then the magic of the first block or of the end of the stream
+fn is_bzip2(header: &[u8]) -> bool {
+    matches!(header.get(3), Some(b'1'..=b'9'))
+        && matches!(
+            header.get(4..10),
+            Some(b"\x31\x41\x59\x26\x53\x59") | Some(b"\x17\x72\x45\x38\x50\x90")
+        )
+}
+
+/// `e_lfanew` points past the DOS header, to `PE\0\0` if it is in the header
+fn is_pe(header: &[u8]) -> bool {
+    let Some(offset) = u32_le(header, 0x3c).map(|o| o as usize) else {
+        return false;
+    };
+
+    if offset < 0x40 {
+        return false;
+    }
+
+    match header.get(offset..offset + 4) {
+        Some(magic) => magic == b"PE\0\0",
+        None => !is_text(header),
+    }
+}
+
+/// Checked in order, so longer signatures come before their prefixes
+const SIGNATURES: &[Signature] = &[
+    // Images
+    sig(0, b"\x89PNG\r\n\x1a\n", "image/png", Category::Image),
+    sig(0, b"\xff\xd8\xff", "image/jpeg", Category::Image),
+    sig(0, b"GIF87a", "image/gif", Category::Image),
+    sig(0, b"GIF89a", "image/gif", Category::Image),
+    sig(8, b"WEBP", "image/webp", Category::Image),
+    sig(0, b"II*\x00", "image/tiff", Category::Image),
+    sig(0, b"MM\x00*", "image/tiff", Category::Image),
+    sig(0, b"\x00\x00\x01\x00", "image/x-icon", Category::Image),
+    sig(4, b"ftypavif", "image/avif", Category::Image),
+    sig(4, b"ftypheic", "image/heic", Category::Image),
+    checked(b"BM", "image/bmp", Category::Image, is_bmp),
+    // Audio
+    checked(b"ID3", "audio/mpeg", Category::Audio, is_id3),
+    sig(0, b"\xff\xfb", "audio/mpeg", Category::Audio),
+    sig(0, b"fLaC", "audio/flac", Category::Audio),
+    sig(0, b"OggS", "audio/ogg", Category::Audio),
+    sig(8, b"WAVE", "audio/wav", Category::Audio),
+    sig(4, b"ftypM4A", "audio/mp4", Category::Audio),
+    sig(0, b"MThd", "audio/midi", Category::Audio),
+    // Video
+    sig(4, b"ftypqt", "video/quicktime", Category::Video),
+    sig(4, b"ftyp", "video/mp4", Category::Video),
+    sig(0, b"\x1a\x45\xdf\xa3", "video/x-matroska", Category::Video),
+    sig(8, b"AVI ", "video/x-msvideo", Category::Video),
+    checked(b"FLV", "video/x-flv", Category::Video, is_flv),
+    // Archives
+    sig(0, b"PK\x03\x04", "application/zip", Category::Archive),
+    sig(0, b"PK\x05\x06", "application/zip", Category::Archive),
+    sig(0, b"\x1f\x8b", "application/gzip", Category::Archive),
+    checked(b"BZh", "application/x-bzip2", Category::Archive, is_bzip2),
+    sig(0, b"\xfd7zXZ\x00", "application/x-xz", Category::Archive),
+    sig(
+        0,
+        b"\x28\xb5\x2f\xfd",
+        "application/zstd",
+        Category::Archive,
+    ),
+    sig(
+        0,
+        b"7z\xbc\xaf\x27\x1c",
+        "application/x-7z-compressed",
+        Category::Archive,
+    ),
+    sig(0, b"Rar!\x1a\x07", "application/vnd.rar", Category::Archive),
+    sig(257, b"ustar", "application/x-tar", Category::Archive),
+    sig(0, b"!<arch>\n", "application/x-archive", Category::Archive),
+    // Documents
+    sig(0, b"%PDF-", "application/pdf", Category::Document),
+    sig(0, b"%!PS", "application/postscript", Category::Document),
+    sig(0, b"{\\rtf", "application/rtf", Category::Document),
+    sig(
+        0,
+        b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+        "application/x-ole-storage",
+        Category::Document,
+    ),
+    // Binaries
+    sig(0, b"\x7fELF", "application/x-executable", Category::Binary),
+    sig(
+        0,
+        b"\xfe\xed\xfa\xce",
+        "application/x-mach-binary",
+        Category::Binary,
+    ),
+    sig(
+        0,
+        b"\xfe\xed\xfa\xcf",
+        "application/x-mach-binary",
+        Category::Binary,
+    ),
+    sig(
+        0,
+        b"\xcf\xfa\xed\xfe",
+        "application/x-mach-binary",
+        Category::Binary,
+    ),
+    sig(
+        0,
+        b"\xca\xfe\xba\xbe",
+        "application/java-vm",
+        Category::Binary,
+    ),
+    sig(0, b"\x00asm", "application/wasm", Category::Binary),
+    sig(
+        0,
+        b"SQLite format 3\x00",
+        "application/vnd.sqlite3",
+        Category::Binary,
+    ),
+    checked(
+        b"MZ",
+        "application/vnd.microsoft.portable-executable",
+        Category::Binary,
+        is_pe,
+    ),
+];
+
+/// Interpreters of `#!` lines
+const SCRIPTS: &[(&str, &str)] = &[
+    ("python", "text/x-python"),
+    ("node", "text/javascript"),
+    ("perl", "text/x-perl"),
+    ("ruby", "text/x-ruby"),
+    ("php", "text/x-php"),
+    ("sh", "text/x-shellscript"),
+    ("bash", "text/x-shellscript"),
+    ("zsh", "text/x-shellscript"),
+    ("fish", "text/x-shellscript"),
+];
+
+/// Text formats recognized by their first non blank characters
+const MARKUP: &[(&str, &str, Category)] = &[
+    ("<?xml", "text/xml", Category::Text),
+    ("<svg", "image/svg+xml", Category::Image),
+    ("<!doctype html", "text/html", Category::Text),
+    ("<html", "text/html", Category::Text),
+];
+
+/// Content type of an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mime {
+    pub mime: String,
+    // `None` for anything that is not a regular file
+    pub category: Option<Category>,
+}
+
+impl Mime {
+    fn new(mime: &str, category: Option<Category>) -> Self {
+        Self {
+            mime: mime.to_string(),
+            category,
+        }
+    }
+
+    /// Detects the type of `path` from its first bytes,
+    /// special files get an `inode/*` type, like `file --mime-type`
+    pub fn detect<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+
+        let Ok(metadata) = path.symlink_metadata() else {
+            return Self::new("inode/x-unknown", None);
+        };
+
+        let file_type = metadata.file_type();
+
+        if !file_type.is_file() {
+            use std::os::unix::fs::FileTypeExt;
+
+            let mime = if file_type.is_dir() {
+                "inode/directory"
+            } else if file_type.is_symlink() {
+                "inode/symlink"
+            } else if file_type.is_fifo() {
+                "inode/fifo"
+            } else if file_type.is_socket() {
+                "inode/socket"
+            } else if file_type.is_block_device() {
+                "inode/blockdevice"
+            } else if file_type.is_char_device() {
+                "inode/chardevice"
+            } else {
+                "inode/x-unknown"
+            };
+
+            return Self::new(mime, None);
+        }
+
+        if metadata.len() == 0 {
+            return Self::new("inode/x-empty", Some(Category::Text));
+        }
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+
+        let read =
+            File::open(path).and_then(|f| f.take(HEADER_LEN as u64).read_to_end(&mut header));
+
+        if read.is_err() {
+            return Self::new("application/octet-stream", Some(Category::Binary));
+        }
+
+        Self::from_header(&header)
+    }
+
+    fn from_header(header: &[u8]) -> Self {
+        for s in SIGNATURES {
+            if header.get(s.offset..s.offset + s.magic.len()) == Some(s.magic)
+                && s.check.is_none_or(|check| check(header))
+            {
+                return Self::new(s.mime, Some(s.category));
+            }
+        }
+
+        if !is_text(header) {
+            return Self::new("application/octet-stream", Some(Category::Binary));
+        }
+
+        let text = String::from_utf8_lossy(header);
+        let text = text.trim_start_matches('\u{feff}');
+
+        if let Some(line) = text.strip_prefix("#!") {
+            let line = line.lines().next().unwrap_or_default();
+
+            // `#!/usr/bin/env python3` or `#!/bin/sh`
+            let interpreter = line
+                .split_whitespace()
+                .find(|w| !w.ends_with("/env") && !w.starts_with('-'))
+                .and_then(|w| w.rsplit('/').next())
+                .unwrap_or_default();
+
+            let mime = SCRIPTS
+                .iter()
+                .find(|(name, _)| interpreter.starts_with(name))
+                .map(|(_, mime)| *mime)
+                .unwrap_or("text/x-script");
+
+            return Self::new(mime, Some(Category::Text));
+        }
+
+        let start = text.trim_start().to_lowercase();
+
+        for (prefix, mime, category) in MARKUP {
+            if start.starts_with(prefix) {
+                return Self::new(mime, Some(*category));
+            }
+        }
+
+        Self::new("text/plain", Some(Category::Text))
+    }
+}
+
+/// Text if there is no NUL byte and it is valid UTF-8,
+/// or UTF-16 with a byte order mark, or almost only printable Latin-1
+pub fn is_text(header: &[u8]) -> bool {
+    if header.starts_with(b"\xff\xfe") || header.starts_with(b"\xfe\xff") {
+        return true;
+    }
+
+    if header.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(header) {
+        Ok(_) => true,
+        // The header may end in the middle of a character
+        Err(e) if e.error_len().is_none() => true,
+        Err(_) => {
+            // C0 and C1 controls are rare in Latin-1 text
+            let control = header
+                .iter()
+                .filter(|&&b| {
+                    (b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0c | 0x1b))
+                        || (0x7f..0xa0).contains(&b)
+                })
+                .count();
+
+            control * 20 < header.len()
+        }
+    }
+}
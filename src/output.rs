@@ -0,0 +1,199 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::{io::Write, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    // Styled, human readable output
+    #[default]
+    Text,
+    // A single JSON array
+    Json,
+    // One JSON object per line
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!("invalid output format: {}", s)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Writes the records in the structured format.
+    ///
+    /// Field names (and the column order for csv/tsv) come from the
+    /// `Serialize` implementation of the record, so they stay stable.
+    pub fn write<T: Serialize, W: Write>(&self, records: &[T], w: &mut W) -> std::io::Result<()> {
+        match self {
+            OutputFormat::Text => Ok(()),
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *w, records)?;
+                writeln!(w)
+            }
+            OutputFormat::Ndjson => {
+                for record in records {
+                    serde_json::to_writer(&mut *w, record)?;
+                    writeln!(w)?;
+                }
+
+                Ok(())
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let rows = records
+                    .iter()
+                    .map(serde_json::to_value)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let Some(Value::Object(first)) = rows.first() else {
+                    return Ok(());
+                };
+
+                let headers: Vec<&str> = first.keys().map(|k| k.as_str()).collect();
+                self.write_line(w, headers.iter().copied())?;
+
+                for row in &rows {
+                    let cells: Vec<String> = headers
+                        .iter()
+                        .map(|h| row.get(*h).map(cell).unwrap_or_default())
+                        .collect();
+
+                    self.write_line(w, cells.iter().map(|c| c.as_str()))?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn write_line<'a, W: Write, I: Iterator<Item = &'a str>>(
+        &self,
+        w: &mut W,
+        cells: I,
+    ) -> std::io::Result<()> {
+        let (separator, escape): (&str, fn(&str) -> String) = match self {
+            OutputFormat::Tsv => ("\t", escape_tsv),
+            _ => (",", escape_csv),
+        };
+
+        let line = cells.map(escape).collect::<Vec<_>>().join(separator);
+        writeln!(w, "{}", line)
+    }
+}
+
+/// Separates the items of array fields (`link_chain`, `xattrs`, `rdev`...) in csv/tsv cells
+const ARRAY_SEPARATOR: &str = ";";
+
+/// A csv/tsv cell: `null` and empty arrays are empty, arrays are joined
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(cell)
+            .collect::<Vec<_>>()
+            .join(ARRAY_SEPARATOR),
+        v => v.to_string(),
+    }
+}
+
+/// Quotes the field if needed, as described in RFC 4180
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Tabs and newlines can't appear in tsv fields, so they are backslash-escaped
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn written(format: OutputFormat, records: &[Value]) -> String {
+        let mut out = Vec::new();
+        format.write(records, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_quotes_only_when_needed() {
+        assert_eq!(escape_csv("plain"), "plain");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("a,\"b"), "\"a,\"\"b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv("two\nlines"), "\"two\nlines\"");
+        assert_eq!(escape_csv("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn tsv_escapes_separators() {
+        assert_eq!(escape_tsv("plain"), "plain");
+        assert_eq!(escape_tsv("a\tb"), "a\\tb");
+        assert_eq!(escape_tsv("two\nlines\r"), "two\\nlines\\r");
+        assert_eq!(escape_tsv("back\\slash"), "back\\\\slash");
+        assert_eq!(escape_tsv("a,\"b"), "a,\"b");
+    }
+
+    #[test]
+    fn cells() {
+        assert_eq!(cell(&Value::Null), "");
+        assert_eq!(cell(&json!("text")), "text");
+        assert_eq!(cell(&json!(42)), "42");
+        assert_eq!(cell(&json!(true)), "true");
+        assert_eq!(cell(&json!([])), "");
+        assert_eq!(cell(&json!(["l", "x.rs"])), "l;x.rs");
+        assert_eq!(cell(&json!([1, 3])), "1;3");
+    }
+
+    #[test]
+    fn csv_rows() {
+        let records = [
+            json!({ "name": "a,\"b", "size": 1, "chain": ["x", "y"] }),
+            json!({ "name": "two\nlines", "size": null, "chain": [] }),
+        ];
+
+        assert_eq!(
+            written(OutputFormat::Csv, &records),
+            "name,size,chain\n\"a,\"\"b\",1,x;y\n\"two\nlines\",,\n"
+        );
+    }
+
+    #[test]
+    fn tsv_rows() {
+        let records = [json!({ "name": "a\tb", "lines": ["1", "2"] })];
+
+        assert_eq!(
+            written(OutputFormat::Tsv, &records),
+            "name\tlines\na\\tb\t1;2\n"
+        );
+    }
+
+    #[test]
+    fn no_records() {
+        assert_eq!(written(OutputFormat::Csv, &[]), "");
+        assert_eq!(written(OutputFormat::Json, &[]), "[]\n");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    config::{EntryGroup, FileKind, SortKey},
+    dirsize::DirSizes,
+};
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ffi::OsString,
+    fs::DirEntry,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+/// Everything an entry can be sorted by,
+/// computed once per entry before sorting
+struct Keys {
+    group: usize,
+    name: OsString,
+    extension: OsString,
+    kind: String,
+    size: u64,
+    // Recursive size for directories
+    total_size: u64,
+    modified: Option<SystemTime>,
+    created: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    uid: u32,
+}
+
+impl Keys {
+    fn new(
+        path: &Path,
+        name: OsString,
+        total_size: Option<u64>,
+        group_order: &[EntryGroup],
+    ) -> std::io::Result<Self> {
+        let (kind, meta) = FileKind::from_path(path)?;
+
+        // Groups that are not listed share the last rank
+        let group = group_order
+            .iter()
+            .position(|g| *g == EntryGroup::of(kind))
+            .unwrap_or(group_order.len());
+
+        Ok(Self {
+            group,
+            name,
+            extension: path.extension().map(|e| e.to_owned()).unwrap_or_default(),
+            kind: kind.to_string(),
+            size: meta.len(),
+            total_size: total_size.unwrap_or(meta.len()),
+            modified: meta.modified().ok(),
+            created: meta.created().ok(),
+            accessed: meta.accessed().ok(),
+            uid: meta.uid(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sorter {
+    keys: Vec<SortKey>,
+    reverse: bool,
+    group_order: Vec<EntryGroup>,
+    sizes: Arc<DirSizes>,
+}
+
+impl Sorter {
+    pub fn new(
+        keys: Vec<SortKey>,
+        reverse: bool,
+        group_order: Vec<EntryGroup>,
+        sizes: Arc<DirSizes>,
+    ) -> Self {
+        Self {
+            keys,
+            reverse,
+            group_order,
+            sizes,
+        }
+    }
+
+    /// Sorts the entries of a single directory.
+    ///
+    /// Sort keys are computed once per entry (in parallel),
+    /// so sorting large directories only costs one `lstat` per entry.
+    pub fn sort(&self, entries: &mut Vec<DirEntry>) {
+        self.sort_with(entries, |e| (e.path(), e.file_name()));
+    }
+
+    /// Sorts paths given on the command line,
+    /// the whole path is used as the name
+    pub fn sort_paths(&self, paths: &mut Vec<PathBuf>) {
+        self.sort_with(paths, |p| (p.clone(), p.as_os_str().to_owned()));
+    }
+
+    fn sort_with<T, F>(&self, items: &mut Vec<T>, path_and_name: F)
+    where
+        T: Send,
+        F: Fn(&T) -> (PathBuf, OsString) + Sync,
+    {
+        // Directory sizes are computed here, outside of the rayon pool,
+        // each walk is parallel already
+        let totals: Vec<Option<u64>> = if self.keys.contains(&SortKey::TotalSize) {
+            items
+                .iter()
+                .map(|item| {
+                    let (path, _) = path_and_name(item);
+                    let is_dir = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+                    is_dir.then(|| self.sizes.get(&path))
+                })
+                .collect()
+        } else {
+            vec![None; items.len()]
+        };
+
+        let results: Vec<Result<(Keys, T), T>> = std::mem::take(items)
+            .into_par_iter()
+            .zip(totals)
+            .map(|(item, total)| {
+                let (path, name) = path_and_name(&item);
+
+                match Keys::new(&path, name, total, &self.group_order) {
+                    Ok(keys) => Ok((keys, item)),
+                    Err(_) => Err(item),
+                }
+            })
+            .collect();
+
+        let mut keyed = Vec::with_capacity(results.len());
+        let mut vanished = Vec::new();
+
+        for result in results {
+            match result {
+                Ok(keyed_item) => keyed.push(keyed_item),
+                Err(item) => vanished.push(item),
+            }
+        }
+
+        let owners = if self.keys.contains(&SortKey::Owner) {
+            Self::owner_names(keyed.iter().map(|(k, _)| k.uid))
+        } else {
+            HashMap::new()
+        };
+
+        keyed.par_sort_by(|(a, _), (b, _)| {
+            // The group order is not affected by `reverse`
+            a.group.cmp(&b.group).then_with(|| {
+                let ord = self.compare(a, b, &owners);
+                if self.reverse { ord.reverse() } else { ord }
+            })
+        });
+
+        items.extend(keyed.into_iter().map(|(_, item)| item));
+
+        // Entries that vanished since they were read come last,
+        // the caller reports them when it fails to read them again
+        items.extend(vanished);
+    }
+
+    fn compare(&self, a: &Keys, b: &Keys, owners: &HashMap<u32, String>) -> Ordering {
+        self.keys
+            .iter()
+            .map(|key| match key {
+                SortKey::Name => a.name.cmp(&b.name),
+                // Largest and newest first, like ls
+                SortKey::Size => b.size.cmp(&a.size),
+                SortKey::Modified => b.modified.cmp(&a.modified),
+                SortKey::Created => b.created.cmp(&a.created),
+                SortKey::Accessed => b.accessed.cmp(&a.accessed),
+                SortKey::Extension => a.extension.cmp(&b.extension),
+                SortKey::Kind => a.kind.cmp(&b.kind),
+                SortKey::Owner => owners.get(&a.uid).cmp(&owners.get(&b.uid)),
+                SortKey::TotalSize => b.total_size.cmp(&a.total_size),
+            })
+            .find(|ord| ord.is_ne())
+            // Always fall back to the name, so the order is deterministic
+            .unwrap_or_else(|| a.name.cmp(&b.name))
+    }
+
+    /// Resolves every distinct uid only once
+    fn owner_names<I: Iterator<Item = u32>>(uids: I) -> HashMap<u32, String> {
+        let mut owners = HashMap::new();
+
+        for uid in uids {
+            owners.entry(uid).or_insert_with(|| {
+                users::get_user_by_uid(uid)
+                    .map(|u| u.name().to_string_lossy().to_string())
+                    .unwrap_or_else(|| uid.to_string())
+            });
+        }
+
+        owners
+    }
+}
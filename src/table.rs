@@ -3,6 +3,11 @@ use std::fmt::Display;
 use strip_ansi_escapes::strip_str;
 use unicode_width::UnicodeWidthStr;
 
+/// Width of the string once printed, ignoring ANSI escape codes
+pub fn visual_width(s: &str) -> usize {
+    strip_str(s).width()
+}
+
 pub struct Table<T: Display> {
     // Matrix of rows, made of vectors of type T
     // T must be printable (Display trait)
@@ -59,10 +64,6 @@ impl<T: Display> Table<T> {
         self.padding = padding;
         self
     }
-
-    pub fn rows(&self) -> &Vec<Vec<(T, Alignment)>> {
-        &self.rows
-    }
 }
 
 impl<T: Display> Display for Table<T> {
@@ -81,7 +82,7 @@ impl<T: Display> Display for Table<T> {
 
         for row in self.rows.iter() {
             for (i, (cell, _)) in row.iter().enumerate() {
-                let len = strip_str(&cell.to_string()).width();
+                let len = visual_width(&cell.to_string());
                 if len > widths[i] {
                     widths[i] = len;
                 }
@@ -91,7 +92,7 @@ impl<T: Display> Display for Table<T> {
         for (i, row) in self.rows.iter().enumerate() {
             for (j, (cell, alignment)) in row.iter().enumerate() {
                 let cell_str = cell.to_string();
-                let visual_width = strip_str(&cell_str).width();
+                let visual_width = visual_width(&cell_str);
                 let available_width = widths[j];
                 let is_last_column = j == row.len() - 1;
 
@@ -163,3 +164,120 @@ impl<T: Display> Display for Table<T> {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridDirection {
+    // Fill each column before moving to the next one (ls -C)
+    Down,
+    // Fill each row before moving to the next one (ls -x)
+    Across,
+}
+
+pub struct Grid {
+    cells: Vec<String>,
+    // Visual width of each cell, cached so they are only measured once
+    widths: Vec<usize>,
+    direction: GridDirection,
+    // Maximum width of a line
+    max_width: usize,
+    // Minimum space between columns
+    padding: usize,
+}
+
+impl Grid {
+    pub fn new(direction: GridDirection, max_width: usize) -> Self {
+        Self {
+            cells: Vec::new(),
+            widths: Vec::new(),
+            direction,
+            max_width,
+            padding: 2,
+        }
+    }
+
+    pub fn add_cell(&mut self, cell: String) {
+        self.widths.push(visual_width(&cell));
+        self.cells.push(cell);
+    }
+
+    pub fn padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    fn index(&self, row: usize, col: usize, rows: usize, cols: usize) -> usize {
+        match self.direction {
+            GridDirection::Down => col * rows + row,
+            GridDirection::Across => row * cols + col,
+        }
+    }
+
+    /// Width of each column if the cells are laid out in `cols` columns,
+    /// or `None` if they don't fit in the maximum width
+    fn column_widths(&self, cols: usize) -> Option<Vec<usize>> {
+        let rows = self.cells.len().div_ceil(cols);
+        let mut widths = vec![0; cols];
+
+        for row in 0..rows {
+            for (col, width) in widths.iter_mut().enumerate() {
+                let i = self.index(row, col, rows, cols);
+
+                if let Some(w) = self.widths.get(i) {
+                    *width = (*width).max(*w);
+                }
+            }
+        }
+
+        let total = widths.iter().sum::<usize>() + self.padding * (cols - 1);
+
+        (total <= self.max_width).then_some(widths)
+    }
+}
+
+impl Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.cells.is_empty() {
+            return Ok(());
+        }
+
+        // Every column holds at least one cell, so there can't
+        // be more columns than what the narrowest cells allow
+        let min_width = self.widths.iter().min().copied().unwrap_or(0);
+        let max_cols =
+            (self.max_width / (min_width + self.padding).max(1)).clamp(1, self.cells.len());
+
+        let (cols, widths) = (1..=max_cols)
+            .rev()
+            .find_map(|cols| self.column_widths(cols).map(|w| (cols, w)))
+            .unwrap_or_else(|| (1, vec![0]));
+
+        let rows = self.cells.len().div_ceil(cols);
+
+        for row in 0..rows {
+            for (col, width) in widths.iter().enumerate() {
+                let i = self.index(row, col, rows, cols);
+
+                let Some(cell) = self.cells.get(i) else {
+                    continue;
+                };
+
+                let is_last_column =
+                    col == cols - 1 || self.index(row, col + 1, rows, cols) >= self.cells.len();
+
+                if is_last_column {
+                    write!(f, "{}", cell)?;
+                    break;
+                }
+
+                let padding = width - self.widths[i] + self.padding;
+                write!(f, "{}{:<padding$}", cell, "", padding = padding)?;
+            }
+
+            if row < rows - 1 {
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
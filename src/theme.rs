@@ -0,0 +1,241 @@
+use crate::config::{If, Style};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{Read, Write},
+    os::fd::AsRawFd,
+    time::{Duration, Instant},
+};
+
+/// Themes embedded in the binary, like the default config
+const BUILTIN: [(&str, &str); 5] = [
+    ("default", include_str!("../themes/default.json")),
+    ("solarized", include_str!("../themes/solarized.json")),
+    ("gruvbox", include_str!("../themes/gruvbox.json")),
+    (
+        "high-contrast",
+        include_str!("../themes/high-contrast.json"),
+    ),
+    ("monochrome", include_str!("../themes/monochrome.json")),
+];
+
+/// How long the terminal has to report its background color
+const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Background {
+    // Asks the terminal, when the theme has variants
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl<'de> Deserialize<'de> for Background {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Background::Auto),
+            "dark" => Ok(Background::Dark),
+            "light" => Ok(Background::Light),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid background: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl Background {
+    /// `Auto` resolved to the background of the terminal, dark if unknown
+    pub fn resolve(self) -> Self {
+        match self {
+            Background::Auto => from_colorfgbg()
+                .or_else(query_terminal)
+                .unwrap_or(Background::Dark),
+            b => b,
+        }
+    }
+}
+
+/// A set of styles, with variants for dark and light backgrounds
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    styles: HashMap<String, If<Style>>,
+    // Replace the styles with the same key
+    dark: HashMap<String, If<Style>>,
+    light: HashMap<String, If<Style>>,
+}
+
+impl Theme {
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(name, _)| *name)
+    }
+
+    /// A built-in theme, or `<config dir>/pls/themes/<name>.json`
+    pub fn load(name: &str) -> Result<Self, String> {
+        let content = match BUILTIN.iter().find(|(n, _)| *n == name) {
+            Some((_, content)) => content.to_string(),
+            None => {
+                let path = dirs::config_dir()
+                    .map(|d| d.join("pls").join("themes").join(format!("{}.json", name)))
+                    .filter(|p| p.exists())
+                    .ok_or_else(|| {
+                        format!(
+                            "unknown theme: {} (built-in themes: {})",
+                            name,
+                            Self::names().collect::<Vec<_>>().join(", ")
+                        )
+                    })?;
+
+                std::fs::read_to_string(&path)
+                    .map_err(|e| format!("cannot read theme '{}': {}", path.display(), e))?
+            }
+        };
+
+        serde_json::from_str(&content).map_err(|e| format!("invalid theme {}: {}", name, e))
+    }
+
+    pub fn has_variants(&self) -> bool {
+        !self.dark.is_empty() || !self.light.is_empty()
+    }
+
+    /// The styles for a background, `Auto` is treated as dark
+    pub fn into_styles(self, background: Background) -> HashMap<String, If<Style>> {
+        let mut styles = self.styles;
+
+        let variant = match background {
+            Background::Light => self.light,
+            Background::Dark | Background::Auto => self.dark,
+        };
+
+        styles.extend(variant);
+        styles
+    }
+}
+
+/// `COLORFGBG` is set by some terminals (rxvt, konsole) as `fg;bg`,
+/// with ANSI color numbers
+fn from_colorfgbg() -> Option<Background> {
+    let value = std::env::var("COLORFGBG").ok()?;
+    let bg: u8 = value.rsplit(';').next()?.parse().ok()?;
+
+    Some(match bg {
+        7 | 9..=15 => Background::Light,
+        _ => Background::Dark,
+    })
+}
+
+/// Asks the terminal for its background color (OSC 11),
+/// `None` if it doesn't answer in time
+fn query_terminal() -> Option<Background> {
+    // SAFETY: isatty only inspects the descriptor
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } != 1 {
+        return None;
+    }
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+
+    let fd = tty.as_raw_fd();
+
+    // SAFETY: termios is plain data, filled by tcgetattr
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return None;
+    }
+
+    // The answer must not wait for a newline, nor be echoed
+    let mut raw = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 0;
+    raw.c_cc[libc::VTIME] = 0;
+
+    // SAFETY: `raw` is a valid termios, restored below
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+        return None;
+    }
+
+    let response = tty
+        .write_all(b"\x1b]11;?\x07")
+        .and_then(|_| tty.flush())
+        .ok()
+        .and_then(|_| read_response(&mut tty));
+
+    // SAFETY: restores the settings read above
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+
+    parse_color(&response?)
+}
+
+/// Reads until the BEL or ST terminating the answer
+fn read_response(tty: &mut std::fs::File) -> Option<String> {
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut response = Vec::new();
+    let mut buf = [0; 64];
+
+    while !response.ends_with(b"\x07") && !response.ends_with(b"\x1b\\") {
+        let remaining = deadline.checked_duration_since(Instant::now())?;
+
+        let mut poll = libc::pollfd {
+            fd: tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        // SAFETY: a single valid pollfd
+        if unsafe { libc::poll(&mut poll, 1, remaining.as_millis() as libc::c_int) } <= 0 {
+            return None;
+        }
+
+        let n = tty.read(&mut buf).ok()?;
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    Some(String::from_utf8_lossy(&response).to_string())
+}
+
+/// `\x1b]11;rgb:RRRR/GGGG/BBBB\x07`, dark if its luminance is below half
+fn parse_color(response: &str) -> Option<Background> {
+    let rgb = response.split("rgb:").nth(1)?;
+    let rgb = rgb
+        .trim_end_matches(['\x07', '\\'])
+        .trim_end_matches('\x1b');
+
+    // Each component has 1 to 4 hex digits
+    let channels: Vec<f64> = rgb
+        .split('/')
+        .map(|c| {
+            // Longer ones would overflow the shift
+            if !(1..=4).contains(&c.len()) {
+                return None;
+            }
+
+            let max = (1u32 << (4 * c.len())) - 1;
+            u32::from_str_radix(c, 16)
+                .ok()
+                .map(|v| v as f64 / max as f64)
+        })
+        .collect::<Option<_>>()?;
+
+    let [r, g, b] = channels[..] else {
+        return None;
+    };
+
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+
+    Some(if luminance < 0.5 {
+        Background::Dark
+    } else {
+        Background::Light
+    })
+}
@@ -0,0 +1,129 @@
+use crate::config::TreeConfig;
+
+/// A single entry of a pre-order traversal, as yielded by `DirWalker`
+pub struct TreeItem<'a> {
+    pub depth: usize,
+    pub is_dir: bool,
+    // Whether the content of a directory was walked, it isn't past the depth limit
+    pub expanded: bool,
+    pub name: &'a str,
+}
+
+/// A line of the rendered tree
+pub enum TreeLine {
+    Entry {
+        // Index of the item in the traversal
+        index: usize,
+        prefix: String,
+        // Set when a chain of single-child directories was collapsed
+        name: Option<String>,
+    },
+    More {
+        prefix: String,
+        count: usize,
+    },
+}
+
+pub struct Tree<'a> {
+    items: &'a [TreeItem<'a>],
+    // Children of each item, the last slot is the (virtual) root
+    children: Vec<Vec<usize>>,
+    config: &'a TreeConfig,
+}
+
+impl<'a> Tree<'a> {
+    pub fn new(items: &'a [TreeItem<'a>], config: &'a TreeConfig) -> Self {
+        let root = items.len();
+        let mut children = vec![Vec::new(); items.len() + 1];
+        let mut stack = vec![root];
+
+        for (i, item) in items.iter().enumerate() {
+            // The parent of an item at depth `d` is at `stack[d]`
+            stack.truncate(item.depth + 1);
+
+            if let Some(&parent) = stack.last() {
+                children[parent].push(i);
+            }
+
+            stack.push(i);
+        }
+
+        Self {
+            items,
+            children,
+            config,
+        }
+    }
+
+    pub fn lines(&self) -> Vec<TreeLine> {
+        let mut lines = Vec::with_capacity(self.items.len());
+        self.render(self.items.len(), "", &mut lines);
+        lines
+    }
+
+    fn render(&self, node: usize, prefix: &str, lines: &mut Vec<TreeLine>) {
+        let children = &self.children[node];
+
+        let shown = match self.config.max_entries {
+            0 => children.len(),
+            max => children.len().min(max),
+        };
+
+        let hidden = children.len() - shown;
+
+        for (i, &child) in children[..shown].iter().enumerate() {
+            let is_last = i + 1 == shown && hidden == 0;
+            let (child, name) = self.collapse(child);
+
+            let glyph = if is_last {
+                &self.config.last
+            } else {
+                &self.config.branch
+            };
+
+            lines.push(TreeLine::Entry {
+                index: child,
+                prefix: format!("{}{}", prefix, glyph),
+                name,
+            });
+
+            let indent = if is_last {
+                &self.config.blank
+            } else {
+                &self.config.vertical
+            };
+
+            self.render(child, &format!("{}{}", prefix, indent), lines);
+        }
+
+        if hidden > 0 {
+            lines.push(TreeLine::More {
+                prefix: format!("{}{}", prefix, self.config.last),
+                count: hidden,
+            });
+        }
+    }
+
+    /// Follows chains of directories that only contain a single directory,
+    /// returning the last one and the joined name (`a/b/c`).
+    /// Stops before a directory that wasn't walked, its content is unknown
+    fn collapse(&self, mut node: usize) -> (usize, Option<String>) {
+        if !self.config.collapse {
+            return (node, None);
+        }
+
+        let mut name: Option<String> = None;
+
+        while self.items[node].is_dir
+            && let [only] = self.children[node][..]
+            && self.items[only].is_dir
+            && self.items[only].expanded
+        {
+            let joined = name.unwrap_or_else(|| self.items[node].name.to_string());
+            name = Some(format!("{}/{}", joined, self.items[only].name));
+            node = only;
+        }
+
+        (node, name)
+    }
+}
@@ -1,3 +1,9 @@
+use std::{collections::HashSet, time::SystemTime};
+use terminal_size::{Width, terminal_size};
+
+/// The nine permission characters, like ls:
+/// the setuid, setgid and sticky bits replace the matching `x`
+/// with `s`/`t` (or `S`/`T` if the `x` bit itself is not set)
 pub fn permissions_to_string(mode: u32) -> String {
     let mut perms = String::with_capacity(9);
 
@@ -5,11 +11,123 @@ pub fn permissions_to_string(mode: u32) -> String {
     let group_perms = (mode >> 3) & 0o7;
     let other_perms = mode & 0o7;
 
-    for &perm in &[user_perms, group_perms, other_perms] {
+    let special = [
+        (mode & 0o4000 != 0, 's'),
+        (mode & 0o2000 != 0, 's'),
+        (mode & 0o1000 != 0, 't'),
+    ];
+
+    for (&perm, (set, c)) in [user_perms, group_perms, other_perms].iter().zip(special) {
         perms.push(if perm & 0o4 != 0 { 'r' } else { '-' });
         perms.push(if perm & 0o2 != 0 { 'w' } else { '-' });
-        perms.push(if perm & 0o1 != 0 { 'x' } else { '-' });
+        perms.push(match (perm & 0o1 != 0, set) {
+            (true, true) => c,
+            (false, true) => c.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
     }
 
     perms
 }
+
+/// The ls-style 10 character mode string, e.g. `drwxr-xr-x`
+pub fn mode_to_string(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o140000 => 's',
+        0o120000 => 'l',
+        0o100000 => '-',
+        0o060000 => 'b',
+        0o040000 => 'd',
+        0o020000 => 'c',
+        0o010000 => 'p',
+        _ => '?',
+    };
+
+    format!("{}{}", file_type, permissions_to_string(mode))
+}
+
+/// The permission bits in octal, including the special ones, e.g. `0755`
+pub fn mode_to_octal(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+/// Splits a device number into its major and minor numbers,
+/// with the glibc encoding used by Linux
+pub fn major_minor(dev: u64) -> (u32, u32) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+
+    (major as u32, minor as u32)
+}
+
+/// Collects every identifier referenced inside `{...}` in the given templates,
+/// e.g. `{ :depth}` references `depth`
+pub fn template_variables<S: AsRef<str>>(templates: &[S]) -> HashSet<String> {
+    let mut variables = HashSet::new();
+
+    for template in templates {
+        let template = template.as_ref().replace("{{", "").replace("}}", "");
+
+        for block in template.split('{').skip(1) {
+            let content = block.split('}').next().unwrap_or_default();
+
+            for ident in content.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                if !ident.is_empty() && !ident.starts_with(|c: char| c.is_ascii_digit()) {
+                    variables.insert(ident.to_string());
+                }
+            }
+        }
+    }
+
+    variables
+}
+
+/// Width of the terminal attached to stdout,
+/// falling back to `$COLUMNS` and then to 80 columns
+pub fn terminal_width() -> usize {
+    if let Some((Width(w), _)) = terminal_size() {
+        return w as usize;
+    }
+
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(80)
+}
+
+/// Humanized distance between `t` and `now`, e.g. "3 minutes ago", "yesterday", "in 2 hours"
+pub fn relative_time(t: SystemTime, now: SystemTime) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const WEEK: u64 = 7 * DAY;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+
+    let (secs, future) = match now.duration_since(t) {
+        Ok(d) => (d.as_secs(), false),
+        Err(e) => (e.duration().as_secs(), true),
+    };
+
+    let (count, unit) = match secs {
+        0..MINUTE => return "just now".to_string(),
+        MINUTE..HOUR => (secs / MINUTE, "minute"),
+        HOUR..DAY => (secs / HOUR, "hour"),
+        DAY..WEEK if secs < 2 * DAY => {
+            return if future { "tomorrow" } else { "yesterday" }.to_string();
+        }
+        DAY..WEEK => (secs / DAY, "day"),
+        WEEK..MONTH => (secs / WEEK, "week"),
+        MONTH..YEAR => (secs / MONTH, "month"),
+        _ => (secs / YEAR, "year"),
+    };
+
+    let plural = if count == 1 { "" } else { "s" };
+
+    if future {
+        format!("in {} {}{}", count, unit, plural)
+    } else {
+        format!("{} {}{} ago", count, unit, plural)
+    }
+}
@@ -1,17 +1,41 @@
+use crate::ignores::Ignores;
 use crossbeam::channel::{Receiver, Sender};
 use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
 use std::{
-    cmp::Ordering,
     fs::{DirEntry, ReadDir},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
-#[derive(Debug, Clone)]
+/// Sorts a whole directory listing in place.
+/// Receives every (non hidden) entry of a single directory at once,
+/// so implementations can compute expensive keys once per entry
+/// instead of once per comparison.
+pub type SortFn = Arc<dyn Fn(&mut Vec<DirEntry>) + Send + Sync>;
+
+#[derive(Clone)]
 pub struct WalkOptions {
     max_depth: usize,
     skip_hidden: bool,
     follow_symlinks: bool,
-    sort_by: Option<fn(&DirEntry, &DirEntry) -> Ordering>,
+    // Skip entries matched by .gitignore, .ignore and .plsignore files
+    ignore_files: bool,
+    // Yield ignored entries (flagged) instead of skipping them
+    show_ignored: bool,
+    sort_by: Option<SortFn>,
+}
+
+impl std::fmt::Debug for WalkOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalkOptions")
+            .field("max_depth", &self.max_depth)
+            .field("skip_hidden", &self.skip_hidden)
+            .field("follow_symlinks", &self.follow_symlinks)
+            .field("ignore_files", &self.ignore_files)
+            .field("show_ignored", &self.show_ignored)
+            .field("sort_by", &self.sort_by.is_some())
+            .finish()
+    }
 }
 
 impl Default for WalkOptions {
@@ -20,30 +44,53 @@ impl Default for WalkOptions {
             max_depth: usize::MAX,
             skip_hidden: true,
             follow_symlinks: false,
+            ignore_files: true,
+            show_ignored: false,
             sort_by: None,
         }
     }
 }
 
+/// A directory being walked
+#[derive(Debug, Clone)]
+struct Dir {
+    depth: usize,
+    // Rules for the content of the directory, if ignore files are enabled
+    ignores: Option<Arc<Ignores>>,
+    // The content of an ignored directory is ignored as well
+    ignored: bool,
+}
+
 #[derive(Debug)]
 enum StackItem {
-    ReadDir(ReadDir, usize),
-    Entries(Vec<DirEntry>, usize, usize), // entries, depth, index
+    ReadDir(ReadDir, Dir),
+    Entries(std::vec::IntoIter<DirEntry>, Dir), // sorted entries
 }
 
 #[derive(Debug)]
 pub struct DirWalker {
+    root: PathBuf,
     stack: Vec<StackItem>,
     options: WalkOptions,
+    started: bool,
 }
 
 impl DirWalker {
-    pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        let rd = std::fs::read_dir(&path).expect("Root directory should be valid");
-        Self {
-            stack: vec![StackItem::ReadDir(rd, 1)],
+    /// Fails if the root directory can't be read
+    pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let rd = std::fs::read_dir(&path)?;
+        let root = Dir {
+            depth: 1,
+            ignores: None,
+            ignored: false,
+        };
+
+        Ok(Self {
+            root: path.as_ref().to_path_buf(),
+            stack: vec![StackItem::ReadDir(rd, root)],
             options: WalkOptions::default(),
-        }
+            started: false,
+        })
     }
 
     pub fn skip_hidden(mut self, skip: bool) -> Self {
@@ -61,35 +108,109 @@ impl DirWalker {
         self
     }
 
-    pub fn sort_by(mut self, sort_fn: fn(&DirEntry, &DirEntry) -> Ordering) -> Self {
-        self.options.sort_by = Some(sort_fn);
+    pub fn ignore_files(mut self, enabled: bool) -> Self {
+        self.options.ignore_files = enabled;
+        self
+    }
+
+    pub fn show_ignored(mut self, show: bool) -> Self {
+        self.options.show_ignored = show;
+        self
+    }
+
+    pub fn sort_by<F>(mut self, sort_fn: F) -> Self
+    where
+        F: Fn(&mut Vec<DirEntry>) + Send + Sync + 'static,
+    {
+        self.options.sort_by = Some(Arc::new(sort_fn));
         self
     }
+
+    fn is_hidden(&self, entry: &DirEntry) -> bool {
+        self.options.skip_hidden
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with('.'))
+    }
+
+    fn is_ignored(entry: &DirEntry, dir: &Dir) -> bool {
+        dir.ignored
+            || dir.ignores.as_ref().is_some_and(|ignores| {
+                let is_dir = entry.file_type().is_ok_and(|ft| ft.is_dir());
+                ignores.is_ignored(entry.path(), is_dir)
+            })
+    }
+
+    /// Pushes the subdirectory of `entry` on the stack,
+    /// if it should be descended into
+    fn descend(&mut self, entry: &DirEntry, dir: &Dir, ignored: bool) {
+        let Ok(ft) = entry.file_type() else {
+            return;
+        };
+
+        // Only follow symlinks if the option is set
+        if ft.is_dir()
+            && dir.depth < self.options.max_depth
+            && (!ft.is_symlink() || self.options.follow_symlinks)
+            && let Ok(subrd) = std::fs::read_dir(entry.path())
+        {
+            let subdir = Dir {
+                depth: dir.depth + 1,
+                ignores: dir.ignores.as_ref().map(|i| i.child(entry.path())),
+                ignored,
+            };
+
+            self.stack.push(StackItem::ReadDir(subrd, subdir));
+        }
+    }
+
+    /// Loads the ignore files that apply to the root
+    fn start(&mut self) {
+        if self.started {
+            return;
+        }
+
+        self.started = true;
+
+        if self.options.ignore_files
+            && let Some(StackItem::ReadDir(_, dir)) = self.stack.first_mut()
+        {
+            dir.ignores = Some(Ignores::new(&self.root));
+        }
+    }
 }
 
 impl Iterator for DirWalker {
-    type Item = (DirEntry, usize);
+    // The entry, its depth, and whether it is ignored
+    // (always false unless ignored entries are shown)
+    type Item = (DirEntry, usize, bool);
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.start();
+
         while let Some(item) = self.stack.last_mut() {
             match item {
-                StackItem::ReadDir(rd, depth) => {
-                    let depth = *depth;
-
-                    if self.options.sort_by.is_some() {
+                StackItem::ReadDir(rd, dir) => {
+                    if let Some(sort_fn) = self.options.sort_by.clone() {
                         // Need to sort, so collect all entries
-                        let rd = std::mem::replace(rd, std::fs::read_dir(".").unwrap());
-                        let mut entries: Vec<DirEntry> =
-                            rd.filter_map(|entry| entry.ok()).collect();
+                        let Some(StackItem::ReadDir(rd, dir)) = self.stack.pop() else {
+                            unreachable!();
+                        };
 
-                        if let Some(sort_fn) = self.options.sort_by {
-                            entries.sort_by(sort_fn);
-                        }
+                        let mut entries: Vec<DirEntry> = rd
+                            .filter_map(|entry| entry.ok())
+                            .filter(|entry| !self.is_hidden(entry))
+                            .filter(|entry| {
+                                self.options.show_ignored || !Self::is_ignored(entry, &dir)
+                            })
+                            .collect();
 
-                        self.stack.pop();
+                        sort_fn(&mut entries);
 
                         if !entries.is_empty() {
-                            self.stack.push(StackItem::Entries(entries, depth, 0));
+                            self.stack
+                                .push(StackItem::Entries(entries.into_iter(), dir));
                         }
 
                         continue;
@@ -105,63 +226,36 @@ impl Iterator for DirWalker {
                         continue;
                     };
 
-                    let Ok(ft) = e.file_type() else {
-                        continue;
-                    };
-
-                    if let Some(name) = e.file_name().to_str() {
-                        if self.options.skip_hidden && name.starts_with('.') {
-                            continue;
-                        }
-                    }
+                    let dir = dir.clone();
 
-                    if ft.is_dir() && depth + 1 <= self.options.max_depth {
-                        // Only follow symlinks if the option is set
-                        if !ft.is_symlink() || self.options.follow_symlinks {
-                            if let Ok(subrd) = std::fs::read_dir(&e.path()) {
-                                self.stack.push(StackItem::ReadDir(subrd, depth + 1));
-                            }
-                        }
+                    if self.is_hidden(&e) {
+                        continue;
                     }
 
-                    return Some((e, depth));
-                }
-
-                StackItem::Entries(entries, depth, index) => {
-                    let depth = *depth;
-                    let index = *index;
+                    let ignored = Self::is_ignored(&e, &dir);
 
-                    if index >= entries.len() {
-                        self.stack.pop();
+                    if ignored && !self.options.show_ignored {
                         continue;
                     }
 
-                    let entry = if entries.is_empty() {
-                        self.stack.pop();
-                        continue;
-                    } else {
-                        // Always take the first element to maintain sorted order
-                        entries.remove(0)
-                    };
+                    self.descend(&e, &dir, ignored);
 
-                    let Ok(ft) = entry.file_type() else {
-                        eprintln!("cannot get ft for {}", entry.path().display());
+                    return Some((e, dir.depth, ignored));
+                }
+
+                StackItem::Entries(entries, dir) => {
+                    let Some(entry) = entries.next() else {
+                        self.stack.pop();
                         continue;
                     };
 
-                    if let Some(name) = entry.file_name().to_str() {
-                        if self.options.skip_hidden && name.starts_with('.') {
-                            continue;
-                        }
-                    }
+                    let dir = dir.clone();
 
-                    if ft.is_dir() && depth + 1 <= self.options.max_depth {
-                        if let Ok(subrd) = std::fs::read_dir(&entry.path()) {
-                            self.stack.push(StackItem::ReadDir(subrd, depth + 1));
-                        }
-                    }
+                    // Ignored entries were already filtered out, unless they are shown
+                    let ignored = self.options.show_ignored && Self::is_ignored(&entry, &dir);
+                    self.descend(&entry, &dir, ignored);
 
-                    return Some((entry, depth));
+                    return Some((entry, dir.depth, ignored));
                 }
             }
         }
@@ -201,6 +295,11 @@ impl ThreadedWalk {
         self
     }
 
+    pub fn ignore_files(mut self, enabled: bool) -> Self {
+        self.options.ignore_files = enabled;
+        self
+    }
+
     fn start(&mut self) {
         if self.started {
             return;
@@ -211,62 +310,64 @@ impl ThreadedWalk {
         let options = self.options.clone();
 
         rayon::spawn(move || {
-            Self::walk(path, &tx, false, &options, 1);
+            // The root is sent like the entries it contains
+            if options.max_depth > 0 {
+                let _ = tx.send((path.clone(), 0));
+            }
+
+            let ignores = options.ignore_files.then(|| Ignores::new(&path));
+            Self::walk(&path, &tx, &options, 0, ignores);
         });
 
         self.rx = Some(rx);
         self.started = true;
     }
 
+    /// Sends the entries of `dir`, directories included, with the depth of `dir`'s
+    /// entries (0 for the root), like `list` does
     fn walk(
-        path: PathBuf,
+        dir: &Path,
         tx: &Sender<(PathBuf, usize)>,
-        is_file: bool,
         options: &WalkOptions,
         depth: usize,
+        ignores: Option<Arc<Ignores>>,
     ) {
         // Check if the maximum depth has been reached
-        if depth > options.max_depth {
+        if depth >= options.max_depth {
             return;
         }
 
-        // Duplicate the sender.send function
-        // to avoid cloning the path, which can improve performance
-        if is_file {
-            // If this is a file, just send the path and return
-            let _ = tx.send((path, depth));
-            return;
-        }
-
-        let Ok(entries) = std::fs::read_dir(&path) else {
-            let _ = tx.send((path, depth));
+        let Ok(entries) = std::fs::read_dir(dir) else {
             return;
         };
 
-        // If this point is reached, it means we are processing a directory
-        // Send the directory path and depth
-        let _ = tx.send((path, depth));
-
-        // Separate into files and directories
         entries
             .par_bridge()
             .into_par_iter()
             .filter_map(|e| e.ok())
             .for_each(|entry| {
-                if let Some(name) = entry.file_name().to_str() {
-                    if name.starts_with('.') && options.skip_hidden {
-                        return;
-                    }
+                if let Some(name) = entry.file_name().to_str()
+                    && name.starts_with('.')
+                    && options.skip_hidden
+                {
+                    return;
                 }
 
                 let path = entry.path();
+                let file_type = entry.file_type();
 
-                match entry.file_type() {
-                    Ok(ft) if ft.is_dir() => {
-                        if options.follow_symlinks || !ft.is_symlink() {
-                            // If it's a directory, recursively walk it
-                            Self::walk(path, tx, false, options, depth + 1);
-                        }
+                if let Some(ignores) = &ignores
+                    && ignores.is_ignored(&path, file_type.as_ref().is_ok_and(|ft| ft.is_dir()))
+                {
+                    return;
+                }
+
+                match file_type {
+                    Ok(ft) if ft.is_dir() && (options.follow_symlinks || !ft.is_symlink()) => {
+                        // If it's a directory, send it and recursively walk it
+                        let ignores = ignores.as_ref().map(|i| i.child(&path));
+                        let _ = tx.send((path.clone(), depth));
+                        Self::walk(&path, tx, options, depth + 1, ignores);
                     }
 
                     Ok(ft) if ft.is_file() => {
@@ -0,0 +1,177 @@
+use std::path::Path;
+
+const SELINUX: &str = "security.selinux";
+const CAPABILITY: &str = "security.capability";
+
+/// Names of the capabilities, indexed by number (`linux/capability.h`)
+const CAPABILITIES: [&str; 41] = [
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+/// Extended attributes of an entry (not following symlinks)
+#[derive(Debug, Clone, Default)]
+pub struct Xattrs {
+    pub names: Vec<String>,
+    // The SELinux security context, e.g. `system_u:object_r:bin_t:s0`
+    pub selinux: Option<String>,
+    // File capabilities, in the `getcap` format (e.g. `cap_net_bind_service+ep`)
+    pub capabilities: Option<String>,
+}
+
+impl Xattrs {
+    /// Empty if the filesystem doesn't support extended attributes
+    pub fn read<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+
+        let Ok(names) = xattr::list(path) else {
+            return Self::default();
+        };
+
+        let mut names: Vec<String> = names.map(|n| n.to_string_lossy().to_string()).collect();
+        names.sort();
+
+        let selinux = names
+            .iter()
+            .any(|n| n == SELINUX)
+            .then(|| xattr::get(path, SELINUX).ok().flatten())
+            .flatten()
+            .map(|v| {
+                String::from_utf8_lossy(&v)
+                    .trim_end_matches('\0')
+                    .to_string()
+            });
+
+        let capabilities = names
+            .iter()
+            .any(|n| n == CAPABILITY)
+            .then(|| xattr::get(path, CAPABILITY).ok().flatten())
+            .flatten()
+            .and_then(|v| decode_capabilities(&v));
+
+        Self {
+            names,
+            selinux,
+            capabilities,
+        }
+    }
+}
+
+/// Decodes a `vfs_cap_data` structure, as stored in `security.capability`.
+///
+/// Capabilities sharing the same sets are grouped, like `getcap` does:
+/// `cap_net_admin,cap_net_raw+ep cap_sys_time+i`
+fn decode_capabilities(data: &[u8]) -> Option<String> {
+    const REVISION_MASK: u32 = 0xff00_0000;
+    const REVISION_1: u32 = 0x0100_0000;
+    const REVISION_2: u32 = 0x0200_0000;
+    const REVISION_3: u32 = 0x0300_0000;
+    const EFFECTIVE: u32 = 0x01;
+
+    let words: Vec<u32> = data
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+
+    let magic = *words.first()?;
+
+    // Revision 1 has 32 bit sets, the others 64 bit ones
+    // (revision 3 adds the root uid of the namespace at the end)
+    let (permitted, inheritable) = match magic & REVISION_MASK {
+        REVISION_1 => (*words.get(1)? as u64, *words.get(2)? as u64),
+        REVISION_2 | REVISION_3 => (
+            *words.get(1)? as u64 | (*words.get(3)? as u64) << 32,
+            *words.get(2)? as u64 | (*words.get(4)? as u64) << 32,
+        ),
+        _ => return None,
+    };
+
+    let effective = magic & EFFECTIVE != 0;
+
+    // (flags, names), in order of first appearance
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+
+    for cap in 0..64 {
+        let p = permitted & (1 << cap) != 0;
+        let i = inheritable & (1 << cap) != 0;
+
+        if !p && !i {
+            continue;
+        }
+
+        let mut flags = String::new();
+
+        if effective {
+            flags.push('e');
+        }
+
+        if i {
+            flags.push('i');
+        }
+
+        if p {
+            flags.push('p');
+        }
+
+        let name = CAPABILITIES
+            .get(cap)
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| format!("cap_{}", cap));
+
+        match groups.iter_mut().find(|(f, _)| *f == flags) {
+            Some((_, names)) => names.push(name),
+            None => groups.push((flags, vec![name])),
+        }
+    }
+
+    if groups.is_empty() {
+        return None;
+    }
+
+    Some(
+        groups
+            .iter()
+            .map(|(flags, names)| format!("{}+{}", names.join(","), flags))
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}